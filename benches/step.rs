@@ -0,0 +1,50 @@
+//
+// Copyright (c) 2023 Martin Green. All rights reserved.
+//
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use game_of_life::game::{random_soup, Life, RuleVariant, SimulationConfig};
+
+
+/// Sides of the square worlds stepped.
+const SIZES: [u32; 3] = [64, 256, 512];
+
+/// Fractions of live cells, from an empty board to a dense soup.
+const DENSITIES: [f32; 3] = [0.0, 0.1, 0.5];
+
+/// Seed of the soups, so every run steps the same boards.
+const SEED: u64 = 0x5eed;
+
+
+/// Return a world of `size` by `size` cells, each alive with a probability of `density`.
+fn soup(size: u32, density: f32) -> Life {
+    let mut life = Life::new(size, size);
+    life.cells = random_soup(life.bounds, SEED, density, RuleVariant::default());
+
+    life
+}
+
+
+fn bench_step(c: &mut Criterion) {
+    let config = SimulationConfig::default();
+
+    let mut group = c.benchmark_group("step");
+    for size in SIZES {
+        for density in DENSITIES {
+            let life = soup(size, density);
+
+            // Report throughput in cells, so runtimes compare across world sizes.
+            group.throughput(Throughput::Elements(u64::from(size * size)));
+            group.bench_with_input(
+                BenchmarkId::new(format!("{size}x{size}"), format!("{:.0}%", density * 100.0)),
+                &life,
+                |b, life| b.iter(|| life.step(&config)),
+            );
+        }
+    }
+    group.finish();
+}
+
+
+criterion_group!(benches, bench_step);
+criterion_main!(benches);
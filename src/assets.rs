@@ -5,7 +5,7 @@
 use bevy::asset::LoadState;
 use bevy::prelude::*;
 
-use crate::AppState;
+use crate::{config, AppState};
 
 
 #[derive(Default, Resource, Deref, DerefMut)]
@@ -16,15 +16,34 @@ pub struct GameAssets(pub Vec<UntypedHandle>);
 pub struct GlyphAtlas(pub Handle<TextureAtlasLayout>, pub Handle<Image>);
 
 
+/// Asset that failed to load, shown to the user in `AppState::Error`.
+#[derive(Resource)]
+pub struct AssetLoadFailure {
+    pub path: String,
+    pub error: String,
+}
+
+
+/// Sent to load the assets that failed to load again.
+#[derive(Event)]
+pub struct RetryAssetLoad;
+
+
 pub struct AssetPlugin;
 
 impl Plugin for AssetPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<GameAssets>()
+            .add_event::<RetryAssetLoad>()
             .add_systems(Startup, load_fontsheet)
             .add_systems(
                 Update,
-                check_fontsheet_loading.run_if(in_state(AppState::Startup)),
+                (
+                    check_fontsheet_loading.run_if(in_state(AppState::Startup)),
+                    retry_asset_load
+                        .run_if(in_state(AppState::Error))
+                        .run_if(on_event::<RetryAssetLoad>()),
+                ),
             );
     }
 }
@@ -36,22 +55,24 @@ fn load_fontsheet(
     mut assets: ResMut<'_, GameAssets>,
     mut texture_atlases: ResMut<'_, Assets<TextureAtlasLayout>>,
 ) {
-    const FONTSHEET_PATH: &str = "cp437_10x10.png";
+    use config::fontsheet::{COLUMNS, GLYPH_SIZE, PATH, ROWS};
 
-    let fontsheet = asset_server.load(FONTSHEET_PATH);
+    let fontsheet = asset_server.load(PATH);
     assets.push(fontsheet.clone().untyped());
 
-    let layout = TextureAtlasLayout::from_grid(UVec2::splat(10), 16, 16, None, None);
+    let layout = TextureAtlasLayout::from_grid(GLYPH_SIZE, COLUMNS, ROWS, None, None);
     commands.insert_resource(GlyphAtlas(texture_atlases.add(layout), fontsheet));
 }
 
 
 fn check_fontsheet_loading(
+    mut commands: Commands<'_, '_>,
     asset_server: Res<'_, AssetServer>,
     assets: Res<'_, GameAssets>,
     mut next_state: ResMut<'_, NextState<AppState>>,
 ) {
     let mut load_state = LoadState::Loaded;
+    let mut failed_path = None;
     for handle in assets.iter() {
         if handle.path().is_some() {
             let handle_id = handle.id();
@@ -61,6 +82,7 @@ fn check_fontsheet_loading(
                     LoadState::Loading => load_state = LoadState::Loading,
                     LoadState::Failed(err) => {
                         load_state = LoadState::Failed(err);
+                        failed_path = handle.path().map(ToString::to_string);
                         break;
                     }
                     LoadState::NotLoaded => {
@@ -81,6 +103,38 @@ fn check_fontsheet_loading(
             info!("Assets loaded");
             next_state.set(AppState::Running);
         }
-        LoadState::Failed(err) => panic!("failed to load assets: {err}"),
+        LoadState::Failed(err) => {
+            let path = failed_path.unwrap_or_default();
+            error!("Failed to load asset '{path}': {err}");
+            commands.insert_resource(AssetLoadFailure {
+                path,
+                error: err.to_string(),
+            });
+            next_state.set(AppState::Error);
+        }
+    }
+}
+
+
+/// Load the assets that failed to load again, and go back to waiting for them.
+fn retry_asset_load(
+    mut commands: Commands<'_, '_>,
+    asset_server: Res<'_, AssetServer>,
+    assets: Res<'_, GameAssets>,
+    mut ev_retry: EventReader<'_, '_, RetryAssetLoad>,
+    mut next_state: ResMut<'_, NextState<AppState>>,
+) {
+    ev_retry.clear();
+
+    for handle in assets.iter() {
+        if let (Some(path), Some(LoadState::Failed(_))) =
+            (handle.path(), asset_server.get_load_state(handle.id()))
+        {
+            info!("Retrying to load asset '{path}'");
+            asset_server.reload(path.clone());
+        }
     }
+
+    commands.remove_resource::<AssetLoadFailure>();
+    next_state.set(AppState::Startup);
 }
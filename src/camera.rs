@@ -2,22 +2,67 @@
 // Copyright (c) 2023 Martin Green. All rights reserved.
 //
 
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
 use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
 use bevy_pixel_camera::{PixelCameraPlugin, PixelViewport, PixelZoom};
 
 use crate::config;
+use crate::game::Life;
+use crate::input::InputAction;
+use crate::ui::PointerOverUi;
 
 
 #[derive(Component)]
 pub struct MainCamera;
 
 
+/// Zoom of the main camera on top of fitting the window, clamped to
+/// `config::camera::MIN_ZOOM..=config::camera::MAX_ZOOM`.
+#[derive(Resource, Deref)]
+pub struct CameraZoom(f32);
+
+impl CameraZoom {
+    /// Zoom in by `steps` of `config::camera::ZOOM_STEP`, or out if negative, within the zoom
+    /// range.
+    fn zoom_by(&mut self, steps: f32) {
+        self.0 = (self.0 * config::camera::ZOOM_STEP.powf(steps))
+            .clamp(config::camera::MIN_ZOOM, config::camera::MAX_ZOOM);
+    }
+}
+
+impl Default for CameraZoom {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+
+/// Whether the camera follows the centroid of the live cells. Panning the camera stops following.
+#[derive(Default, Resource, Deref, DerefMut)]
+pub struct FollowLiveCells(pub bool);
+
+
 pub struct CameraPlugin;
 
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(PixelCameraPlugin)
-            .add_systems(Startup, setup_camera);
+            .add_systems(Startup, setup_camera)
+            .init_resource::<CameraZoom>()
+            .init_resource::<FollowLiveCells>()
+            .add_systems(
+                Update,
+                (
+                    pan_camera,
+                    pan_camera_on_touch,
+                    follow_live_cells.run_if(|follow: Res<'_, FollowLiveCells>| **follow),
+                    zoom_camera_on_scroll,
+                    zoom_camera.run_if(on_event::<InputAction>()),
+                    reset_camera.run_if(on_event::<InputAction>()),
+                )
+                    .chain(),
+            );
     }
 }
 
@@ -27,10 +72,217 @@ fn setup_camera(mut commands: Commands<'_, '_>) {
     commands.spawn((
         Camera2dBundle::default(),
         PixelZoom::FitSize {
-            width: config::window::WIDTH as i32,
-            height: config::window::HEIGHT as i32,
+            width: config::window::width() as i32,
+            height: config::window::height() as i32,
         },
         PixelViewport,
         MainCamera,
     ));
 }
+
+
+/// Pan the camera while the middle mouse button is held down.
+fn pan_camera(
+    buttons: Res<'_, ButtonInput<MouseButton>>,
+    pointer_over_ui: Res<'_, PointerOverUi>,
+    q_window: Query<'_, '_, &Window, With<PrimaryWindow>>,
+    mut q_camera: Query<'_, '_, (&Camera, &GlobalTransform, &mut Transform), With<MainCamera>>,
+    mut last_cursor: Local<'_, Option<Vec2>>,
+    mut follow: ResMut<'_, FollowLiveCells>,
+) {
+    if !buttons.pressed(MouseButton::Middle) || **pointer_over_ui {
+        *last_cursor = None;
+        return;
+    }
+
+    if **follow {
+        **follow = false;
+    }
+
+    let Some(cursor) = q_window.get_single().ok().and_then(Window::cursor_position) else {
+        return;
+    };
+    let Ok((camera, global_transform, mut transform)) = q_camera.get_single_mut() else {
+        warn!("No main camera");
+        return;
+    };
+
+    // Move the camera so the world position under the cursor stays under the cursor.
+    if let Some(last) = last_cursor.replace(cursor) {
+        if let (Some(from), Some(to)) = (
+            camera.viewport_to_world_2d(global_transform, last),
+            camera.viewport_to_world_2d(global_transform, cursor),
+        ) {
+            transform.translation += (from - to).extend(0.0);
+        }
+    }
+}
+
+
+/// Pan the camera while dragging with two fingers, following the midpoint between them.
+fn pan_camera_on_touch(
+    touches: Res<'_, Touches>,
+    mut q_camera: Query<'_, '_, (&Camera, &GlobalTransform, &mut Transform), With<MainCamera>>,
+    mut follow: ResMut<'_, FollowLiveCells>,
+) {
+    let mut fingers = touches.iter();
+    let (Some(a), Some(b), None) = (fingers.next(), fingers.next(), fingers.next()) else {
+        return;
+    };
+
+    let last = (a.previous_position() + b.previous_position()) / 2.0;
+    let midpoint = (a.position() + b.position()) / 2.0;
+    if last == midpoint {
+        return;
+    }
+
+    if **follow {
+        **follow = false;
+    }
+
+    let Ok((camera, global_transform, mut transform)) = q_camera.get_single_mut() else {
+        warn!("No main camera");
+        return;
+    };
+
+    // Move the camera so the world position under the fingers stays under the fingers.
+    if let (Some(from), Some(to)) = (
+        camera.viewport_to_world_2d(global_transform, last),
+        camera.viewport_to_world_2d(global_transform, midpoint),
+    ) {
+        transform.translation += (from - to).extend(0.0);
+    }
+}
+
+
+/// Move the camera smoothly toward the centroid of the live cells.
+fn follow_live_cells(
+    time: Res<'_, Time>,
+    life: Res<'_, Life>,
+    mut q_camera: Query<'_, '_, &mut Transform, With<MainCamera>>,
+) {
+    let (sum, count) = life
+        .cells
+        .iter()
+        .filter(|(_, cell)| cell.alive)
+        .fold((Vec2::ZERO, 0u32), |(sum, count), (xy, _)| {
+            (sum + xy.as_vec2(), count + 1)
+        });
+    if count == 0 {
+        return;
+    }
+
+    let Ok(mut transform) = q_camera.get_single_mut() else {
+        warn!("No main camera");
+        return;
+    };
+
+    // Cells span from multiples of the sprite size, so their centers are offset by half a sprite.
+    #[allow(clippy::cast_precision_loss)]
+    let centroid =
+        sum / count as f32 * config::cells::sprite_size() + config::cells::sprite_world_offset();
+
+    // Frame rate independent exponential smoothing.
+    let t = 1.0 - (-config::camera::FOLLOW_RATE * time.delta_seconds()).exp();
+    let target = centroid.extend(transform.translation.z);
+    transform.translation = transform.translation.lerp(target, t);
+}
+
+
+/// Zoom the camera with the mouse wheel, keeping the world position under the cursor in place.
+fn zoom_camera_on_scroll(
+    mut ev_wheel: EventReader<'_, '_, MouseWheel>,
+    pointer_over_ui: Res<'_, PointerOverUi>,
+    q_window: Query<'_, '_, &Window, With<PrimaryWindow>>,
+    mut q_camera: Query<
+        '_,
+        '_,
+        (
+            &Camera,
+            &GlobalTransform,
+            &mut Transform,
+            &mut OrthographicProjection,
+        ),
+        With<MainCamera>,
+    >,
+    mut zoom: ResMut<'_, CameraZoom>,
+) {
+    /// Approximate number of pixels per line for high-resolution wheels and touchpads.
+    const PIXELS_PER_LINE: f32 = 100.0;
+
+    let lines = ev_wheel
+        .read()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / PIXELS_PER_LINE,
+        })
+        .sum::<f32>();
+    if lines == 0.0 || **pointer_over_ui {
+        return;
+    }
+
+    let Ok((camera, global_transform, mut transform, mut projection)) = q_camera.get_single_mut()
+    else {
+        warn!("No main camera");
+        return;
+    };
+
+    let old_zoom = zoom.0;
+    zoom.zoom_by(lines);
+    projection.scale = 1.0 / zoom.0;
+
+    // Scale the camera's offset from the world position under the cursor, so that position stays
+    // under the cursor.
+    if let Some(anchor) = q_window
+        .get_single()
+        .ok()
+        .and_then(Window::cursor_position)
+        .and_then(|cursor| camera.viewport_to_world_2d(global_transform, cursor))
+    {
+        let offset = transform.translation.truncate() - anchor;
+        transform.translation =
+            (anchor + offset * (old_zoom / zoom.0)).extend(transform.translation.z);
+    }
+}
+
+
+/// Zoom the camera in steps, e.g. with the keyboard, keeping the center of the view in place.
+fn zoom_camera(
+    mut actions: EventReader<'_, '_, InputAction>,
+    mut q_projection: Query<'_, '_, &mut OrthographicProjection, With<MainCamera>>,
+    mut zoom: ResMut<'_, CameraZoom>,
+) {
+    for action in actions.read() {
+        if let InputAction::ZoomCamera(steps) = action {
+            let Ok(mut projection) = q_projection.get_single_mut() else {
+                warn!("No main camera");
+                return;
+            };
+
+            #[allow(clippy::cast_precision_loss)]
+            zoom.zoom_by(*steps as f32);
+            projection.scale = 1.0 / zoom.0;
+        }
+    }
+}
+
+
+/// Center the camera on the origin and restore the default zoom.
+fn reset_camera(
+    mut actions: EventReader<'_, '_, InputAction>,
+    mut q_camera: Query<'_, '_, (&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+    mut zoom: ResMut<'_, CameraZoom>,
+) {
+    for action in actions.read() {
+        if let InputAction::ResetCamera = action {
+            let Ok((mut transform, mut projection)) = q_camera.get_single_mut() else {
+                warn!("No main camera");
+                return;
+            };
+
+            *zoom = CameraZoom::default();
+            projection.scale = 1.0 / zoom.0;
+            transform.translation = Vec3::new(0.0, 0.0, transform.translation.z);
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//
+// Copyright (c) 2023 Martin Green. All rights reserved.
+//
+
+use std::path::PathBuf;
+
+use crate::config;
+use crate::game::Rule;
+
+
+/// Options given on the command line, e.g. `--pattern glider.rle --rule B36/S23 --tps 10`, or in
+/// the URL query string on the web, e.g. `?rule=B36/S23&tps=10`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Args {
+    /// RLE file to start with, instead of the startup pattern.
+    pub pattern: Option<PathBuf>,
+    pub rule: Option<Rule>,
+    pub ticks_per_second: Option<i32>,
+}
+
+impl Args {
+    /// Parse the command line options of the running program.
+    ///
+    /// Problems with the options don't prevent startup. The options are ignored instead, and
+    /// described in `warnings`, to be logged once logging is set up.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_env(warnings: &mut Vec<String>) -> Self {
+        Self::parse(std::env::args().skip(1), warnings)
+    }
+
+    /// Parse the options in the URL query string of the page, as there is no command line on the
+    /// web.
+    #[cfg(target_arch = "wasm32")]
+    pub fn from_env(warnings: &mut Vec<String>) -> Self {
+        web_sys::window()
+            .and_then(|window| window.location().search().ok())
+            .map(|query| Self::from_query(&query, warnings))
+            .unwrap_or_default()
+    }
+
+    /// Parse options of the form `--name value` or `--name=value`.
+    pub fn parse(args: impl IntoIterator<Item = String>, warnings: &mut Vec<String>) -> Self {
+        let mut parsed = Self::default();
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let Some(option) = arg.strip_prefix("--") else {
+                warnings.push(format!("Ignoring argument `{arg}`"));
+                continue;
+            };
+
+            let (name, value) = match option.split_once('=') {
+                Some((name, value)) => (name, Some(String::from(value))),
+                None => (option, args.next()),
+            };
+            let Some(value) = value else {
+                warnings.push(format!("Ignoring `--{name}`: missing value"));
+                continue;
+            };
+
+            parsed.set(name, value, warnings);
+        }
+
+        parsed
+    }
+
+    /// Parse options from a URL query string of the form `?name=value&name=value`.
+    pub fn from_query(query: &str, warnings: &mut Vec<String>) -> Self {
+        let mut parsed = Self::default();
+
+        let query = query.strip_prefix('?').unwrap_or(query);
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let Some((name, value)) = pair.split_once('=') else {
+                warnings.push(format!("Ignoring `{pair}`: missing value"));
+                continue;
+            };
+
+            parsed.set(name, percent_decode(value), warnings);
+        }
+
+        parsed
+    }
+
+    fn set(&mut self, name: &str, value: String, warnings: &mut Vec<String>) {
+        match name {
+            "pattern" => self.pattern = Some(PathBuf::from(value)),
+            "rule" => match Rule::parse(&value) {
+                Ok(rule) => self.rule = Some(rule),
+                Err(err) => warnings.push(format!("Ignoring `{name}`: {err}")),
+            },
+            "tps" => {
+                let range = config::sim::MIN_TICKS_PER_SECOND..=config::sim::MAX_TICKS_PER_SECOND;
+                match value.parse::<i32>() {
+                    Ok(tps) if range.contains(&tps) => self.ticks_per_second = Some(tps),
+                    _ => warnings.push(format!(
+                        "Ignoring `{name}`: `{value}` isn't within {}..={}",
+                        range.start(),
+                        range.end()
+                    )),
+                }
+            }
+            _ => warnings.push(format!("Ignoring unknown option `{name}`")),
+        }
+    }
+}
+
+
+/// Decode `%XX` escapes and `+` for spaces in a URL query value. Malformed escapes are kept as is.
+fn percent_decode(value: &str) -> String {
+    let mut bytes = Vec::with_capacity(value.len());
+
+    let mut input = value.bytes();
+    while let Some(byte) = input.next() {
+        match byte {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let hex = input.clone().take(2).collect::<Vec<_>>();
+                match std::str::from_utf8(&hex)
+                    .ok()
+                    .filter(|hex| hex.len() == 2)
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                {
+                    Some(decoded) => {
+                        bytes.push(decoded);
+                        input.nth(1);
+                    }
+                    None => bytes.push(byte),
+                }
+            }
+            _ => bytes.push(byte),
+        }
+    }
+
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{percent_decode, Args};
+    use crate::game::Rule;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().copied().map(String::from).collect()
+    }
+
+    #[test]
+    pub fn test_parse_args() {
+        let mut warnings = Vec::new();
+        let parsed = Args::parse(
+            args(&["--pattern", "glider.rle", "--rule=B36/S23", "--tps", "10"]),
+            &mut warnings,
+        );
+        assert_eq!(
+            parsed,
+            Args {
+                pattern: Some(Path::new("glider.rle").to_path_buf()),
+                rule: Some(Rule::parse("B36/S23").unwrap()),
+                ticks_per_second: Some(10),
+            }
+        );
+        assert!(warnings.is_empty());
+
+        // Invalid options are ignored.
+        let parsed = Args::parse(
+            args(&[
+                "stray",
+                "--rule",
+                "B9/S23",
+                "--tps=0",
+                "--speed",
+                "1",
+                "--pattern",
+            ]),
+            &mut warnings,
+        );
+        assert_eq!(parsed, Args::default());
+        assert_eq!(warnings.len(), 5);
+    }
+
+    #[test]
+    pub fn test_args_from_query() {
+        let mut warnings = Vec::new();
+        let parsed = Args::from_query(
+            "?rule=B36%2FS23&tps=10&pattern=my+glider.rle",
+            &mut warnings,
+        );
+        assert_eq!(parsed.rule, Some(Rule::parse("B36/S23").unwrap()));
+        assert_eq!(parsed.ticks_per_second, Some(10));
+        assert_eq!(parsed.pattern.as_deref(), Some(Path::new("my glider.rle")));
+        assert!(warnings.is_empty());
+
+        assert_eq!(Args::from_query("", &mut warnings), Args::default());
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    pub fn test_percent_decode() {
+        assert_eq!(percent_decode("B3%2fS23"), "B3/S23");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%4"), "%zz%4");
+    }
+}
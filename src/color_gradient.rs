@@ -2,35 +2,102 @@
 // Copyright (c) 2023 Martin Green <martin@bk2x.com>. All rights reserved.
 //
 
+use std::io;
+use std::path::Path;
+
+use bevy::color::{HexColorError, Hsva, Mix, Oklaba};
 use bevy::math::VectorSpace;
 use bevy::prelude::*;
 use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
+
+
+/// Largest distance from a sampling point at which [`ColorGradient::remove`] still removes it.
+const REMOVE_TOLERANCE: f32 = 0.01;
+
+
+/// Color space the gradient is interpolated in between sampling points.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Interpolation {
+    /// Linear interpolation of the sRGB components.
+    #[default]
+    LinearRgb,
+    /// Interpolation in the HSV color space, along the shorter hue path.
+    Hsv,
+    /// Interpolation in the perceptually uniform Oklab color space.
+    Oklab,
+}
 
 
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "SavedGradient", into = "SavedGradient")]
 pub struct ColorGradient {
     /// Sampling points.
     points: Vec<ColorPoint>,
+    interpolation: Interpolation,
 }
 
 impl ColorGradient {
     /// Create a new gradient without any sampling points.
     pub fn new() -> Self {
-        Self { points: Vec::new() }
+        Self {
+            points: Vec::new(),
+            interpolation: Interpolation::default(),
+        }
     }
 
-    /// Insert a sampling point into the gradient.
+    /// Return the color space the gradient is interpolated in.
+    pub fn interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+
+    /// Set the color space the gradient is interpolated in.
+    pub fn set_interpolation(&mut self, interpolation: Interpolation) {
+        self.interpolation = interpolation;
+    }
+
+    /// Insert a sampling point into the gradient. An existing sampling point at the same point is
+    /// replaced.
     pub fn insert(&mut self, point: ColorPoint) {
-        // @TODO:
-        // Check if this gradient already has a sampling point at `point.point`. If it does, replace
-        // it with the new one.
-        self.points.push(point);
-        self.points.sort_unstable_by_key(|pt| pt.point);
+        match self
+            .points
+            .binary_search_by_key(&point.point, |sample_pt| sample_pt.point)
+        {
+            Ok(i) => self.points[i] = point,
+            Err(i) => self.points.insert(i, point),
+        }
+    }
+
+    /// Remove the sampling point nearest to the given point, if it lies within
+    /// [`REMOVE_TOLERANCE`] of it. `point` will be clamped to [0.0, 1.0] range. Returns `true` if
+    /// a sampling point was removed.
+    pub fn remove(&mut self, point: f32) -> bool {
+        let point = point.clamp(0.0, 1.0);
+
+        let nearest = self
+            .points
+            .iter()
+            .enumerate()
+            .map(|(i, sample_pt)| (i, (sample_pt.point() - point).abs()))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b));
+        match nearest {
+            Some((i, distance)) if distance <= REMOVE_TOLERANCE => {
+                self.points.remove(i);
+                true
+            }
+            _ => false,
+        }
     }
 
-    /// Sample the gradient at the given point. `point` will be clamped to [0.0, 1.0] range. Panics
-    /// if the gradient has less than 2 sampling points
+    /// Sample the gradient at the given point. `point` will be clamped to [0.0, 1.0] range. A
+    /// gradient with a single sampling point is that point's color everywhere, and an empty
+    /// gradient is transparent black ([`Srgba::NONE`]).
     pub fn sample(&self, point: f32) -> Srgba {
-        assert!(self.points.len() >= 2);
+        match self.points.as_slice() {
+            [] => return Srgba::NONE,
+            [only] => return only.value,
+            _ => {}
+        }
 
         let point: OrderedFloat<f32> = point.clamp(0.0, 1.0).into();
 
@@ -57,18 +124,78 @@ impl ColorGradient {
 
                 // Remap the sampling point into the range between `left` and `right` for
                 // interpolation.
-                left.value.lerp(
-                    right.value,
-                    ((point - left.point) / (right.point - left.point)).into(),
-                )
+                let t: f32 = ((point - left.point) / (right.point - left.point)).into();
+                match self.interpolation {
+                    Interpolation::LinearRgb => left.value.lerp(right.value, t),
+                    Interpolation::Hsv => Hsva::from(left.value)
+                        .mix(&Hsva::from(right.value), t)
+                        .into(),
+                    Interpolation::Oklab => Oklaba::from(left.value)
+                        .mix(&Oklaba::from(right.value), t)
+                        .into(),
+                }
             }
         }
     }
 
+    /// Precompute `steps` evenly spaced samples of the gradient over [0.0, 1.0] range for use with
+    /// [`ColorGradient::sample_baked`].
+    pub fn bake(&self, steps: usize) -> Vec<Srgba> {
+        #[allow(clippy::cast_precision_loss)]
+        let last = steps.saturating_sub(1).max(1) as f32;
+
+        #[allow(clippy::cast_precision_loss)]
+        (0..steps).map(|i| self.sample(i as f32 / last)).collect()
+    }
+
+    /// Sample a gradient baked by [`ColorGradient::bake`] at the nearest precomputed sample.
+    /// `point` will be clamped to [0.0, 1.0] range. Panics if `baked` is empty.
+    pub fn sample_baked(baked: &[Srgba], point: f32) -> Srgba {
+        assert!(!baked.is_empty());
+
+        #[allow(
+            clippy::cast_possible_truncation,
+            clippy::cast_precision_loss,
+            clippy::cast_sign_loss
+        )]
+        let i = (point.clamp(0.0, 1.0) * (baked.len() - 1) as f32).round() as usize;
+        baked[i]
+    }
+
+    /// Return the sampling points of this gradient, ordered by point.
+    pub fn points(&self) -> &[ColorPoint] {
+        &self.points
+    }
+
     /// Return the number of sampling points in this gradient.
     pub fn len(&self) -> usize {
         self.points.len()
     }
+
+    /// Return `true` if this gradient has no sampling points.
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Save the gradient to `path` in the RON format.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the gradient can't be written to `path`.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let ron = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(io::Error::other)?;
+        std::fs::write(path, ron)
+    }
+
+    /// Load a gradient saved by [`ColorGradient::save`] from `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `path` can't be read or isn't a valid gradient.
+    pub fn load(path: &Path) -> Result<Self, LoadError> {
+        Ok(ron::from_str(&std::fs::read_to_string(path)?)?)
+    }
 }
 
 impl Default for ColorGradient {
@@ -79,11 +206,83 @@ impl Default for ColorGradient {
                 ColorPoint::new(0.0, Srgba::new(0.0, 0.0, 0.0, 1.0)),
                 ColorPoint::new(1.0, Srgba::new(1.0, 1.0, 1.0, 1.0)),
             ],
+            interpolation: Interpolation::default(),
+        }
+    }
+}
+
+/// Serialized form of a [`ColorGradient`]. The sampling points are inserted one by one when
+/// deserializing, so they end up sorted and without duplicates regardless of the input.
+#[derive(Serialize, Deserialize)]
+struct SavedGradient {
+    points: Vec<ColorPoint>,
+    #[serde(default)]
+    interpolation: Interpolation,
+}
+
+impl From<SavedGradient> for ColorGradient {
+    fn from(saved: SavedGradient) -> Self {
+        let mut gradient = Self::new();
+        gradient.set_interpolation(saved.interpolation);
+        for point in saved.points {
+            gradient.insert(point);
+        }
+
+        gradient
+    }
+}
+
+impl From<ColorGradient> for SavedGradient {
+    fn from(gradient: ColorGradient) -> Self {
+        Self {
+            points: gradient.points,
+            interpolation: gradient.interpolation,
         }
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    Parse(ron::error::SpannedError),
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => err.fmt(f),
+            Self::Parse(err) => write!(f, "invalid gradient: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Parse(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for LoadError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        Self::Parse(err)
+    }
+}
+
+
+/// Sampling point of a [`ColorGradient`]. Serialized as the point and the color in hex, e.g.
+/// `(point: 0.5, value: "#FFD300")`.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(try_from = "SavedColorPoint", into = "SavedColorPoint")]
 pub struct ColorPoint {
     point: OrderedFloat<f32>,
     value: Srgba,
@@ -97,13 +296,54 @@ impl ColorPoint {
             value,
         }
     }
+
+    /// Return the point on the gradient, in [0.0, 1.0] range.
+    pub fn point(&self) -> f32 {
+        self.point.into()
+    }
+
+    /// Return the color value at the point.
+    pub fn value(&self) -> Srgba {
+        self.value
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedColorPoint {
+    point: f32,
+    value: String,
+}
+
+impl TryFrom<SavedColorPoint> for ColorPoint {
+    type Error = HexColorError;
+
+    fn try_from(saved: SavedColorPoint) -> Result<Self, Self::Error> {
+        Ok(Self::new(saved.point, Srgba::hex(&saved.value)?))
+    }
+}
+
+impl From<ColorPoint> for SavedColorPoint {
+    fn from(point: ColorPoint) -> Self {
+        Self {
+            point: point.point(),
+            value: point.value.to_hex(),
+        }
+    }
 }
 
+
+/// Convert a color component from `0.0..=1.0` to `0..=255`, clamping it.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn to_u8(component: f32) -> u8 {
+    (component.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+
 #[cfg(test)]
 mod tests {
     use bevy::prelude::*;
 
-    use super::{ColorGradient, ColorPoint};
+    use super::{ColorGradient, ColorPoint, Interpolation};
 
     #[test]
     pub fn test_default_gradient() {
@@ -140,4 +380,101 @@ mod tests {
         assert_eq!(gradient.sample(1.0), Srgba::new(1.0, 1.0, 1.0, 1.0));
         assert_eq!(gradient.sample(1.1), Srgba::new(1.0, 1.0, 1.0, 1.0));
     }
+
+    #[test]
+    pub fn test_degenerate_gradient() {
+        let mut gradient = ColorGradient::new();
+        assert_eq!(gradient.sample(0.5), Srgba::NONE);
+
+        gradient.insert(ColorPoint::new(0.3, Srgba::new(1.0, 0.0, 0.0, 1.0)));
+        assert_eq!(gradient.sample(0.0), Srgba::new(1.0, 0.0, 0.0, 1.0));
+        assert_eq!(gradient.sample(0.3), Srgba::new(1.0, 0.0, 0.0, 1.0));
+        assert_eq!(gradient.sample(1.0), Srgba::new(1.0, 0.0, 0.0, 1.0));
+        assert_eq!(gradient.bake(4), [Srgba::new(1.0, 0.0, 0.0, 1.0); 4]);
+    }
+
+    #[test]
+    pub fn test_insert_replaces_point() {
+        let mut gradient = ColorGradient::default();
+
+        gradient.insert(ColorPoint::new(0.5, Srgba::new(1.0, 0.0, 0.0, 1.0)));
+        assert_eq!(gradient.len(), 3);
+
+        gradient.insert(ColorPoint::new(0.5, Srgba::new(0.0, 1.0, 0.0, 1.0)));
+        assert_eq!(gradient.len(), 3);
+        assert_eq!(gradient.sample(0.5), Srgba::new(0.0, 1.0, 0.0, 1.0));
+    }
+
+    #[test]
+    pub fn test_baked_gradient() {
+        const STEPS: usize = 256;
+
+        let gradient = ColorGradient::default();
+        let baked = gradient.bake(STEPS);
+        assert_eq!(baked.len(), STEPS);
+
+        #[allow(clippy::cast_precision_loss)]
+        let epsilon = 1.0 / (STEPS - 1) as f32;
+        for i in 0..=1000u16 {
+            let point = f32::from(i) / 1000.0;
+            let direct = gradient.sample(point);
+            let baked = ColorGradient::sample_baked(&baked, point);
+
+            assert!((direct.red - baked.red).abs() <= epsilon);
+            assert!((direct.green - baked.green).abs() <= epsilon);
+            assert!((direct.blue - baked.blue).abs() <= epsilon);
+            assert!((direct.alpha - baked.alpha).abs() <= epsilon);
+        }
+    }
+
+    #[test]
+    pub fn test_remove_point() {
+        let mut gradient = ColorGradient::default();
+
+        assert!(!gradient.remove(0.5));
+        assert!(gradient.remove(1.0));
+        assert!(gradient.remove(0.0));
+        assert!(gradient.is_empty());
+
+        // The nearest sampling point within the tolerance is removed.
+        gradient.insert(ColorPoint::new(0.3, Srgba::RED));
+        gradient.insert(ColorPoint::new(0.31, Srgba::GREEN));
+        assert!(!gradient.remove(0.33));
+        assert!(gradient.remove(0.302));
+        assert_eq!(gradient.len(), 1);
+        assert!(gradient.remove(0.315));
+        assert!(gradient.is_empty());
+    }
+
+    #[test]
+    pub fn test_serde_round_trip() {
+        let mut gradient = ColorGradient::new();
+        gradient.set_interpolation(Interpolation::Oklab);
+        gradient.insert(ColorPoint::new(0.0, Srgba::rgb_u8(143, 0, 255)));
+        gradient.insert(ColorPoint::new(0.4, Srgba::rgb_u8(255, 121, 0)));
+        gradient.insert(ColorPoint::new(1.0, Srgba::rgba_u8(0, 183, 235, 128)));
+
+        let ron = ron::to_string(&gradient).unwrap();
+        let loaded: ColorGradient = ron::from_str(&ron).unwrap();
+        assert_eq!(loaded.interpolation(), Interpolation::Oklab);
+        assert_eq!(loaded.len(), 3);
+        for i in 0..=100u8 {
+            let point = f32::from(i) / 100.0;
+            assert_eq!(loaded.sample(point), gradient.sample(point));
+        }
+
+        // Points are sorted and deduplicated when loading.
+        let loaded: ColorGradient = ron::from_str(
+            "(points: [(point: 1.0, value: \"#FFFFFF\"), (point: 0.0, value: \"#000000\"), \
+             (point: 1.0, value: \"#FF0000\")])",
+        )
+        .unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded.interpolation(), Interpolation::LinearRgb);
+        assert_eq!(loaded.sample(1.0), Srgba::new(1.0, 0.0, 0.0, 1.0));
+
+        assert!(
+            ron::from_str::<ColorGradient>("(points: [(point: 0.5, value: \"red\")])").is_err()
+        );
+    }
 }
@@ -2,41 +2,497 @@
 // Copyright (c) 2023 Martin Green. All rights reserved.
 //
 
+use std::path::Path;
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+
+
+/// Optional file, relative to the working directory, overriding some of the defaults below.
+pub const OVERRIDES_PATH: &str = "config.toml";
+
+
+/// Overrides of the defaults below, loaded from [`OVERRIDES_PATH`]. Missing fields fall back to
+/// the defaults.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Overrides {
+    pub window: WindowOverrides,
+    pub sim: SimOverrides,
+    pub cells: CellsOverrides,
+    pub editor: EditorOverrides,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowOverrides {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SimOverrides {
+    pub ticks_per_second: Option<i32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CellsOverrides {
+    pub sprite_size: Option<[f32; 2]>,
+    pub age_gradient: Option<Vec<GradientStop>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EditorOverrides {
+    pub max_fill_cells: Option<usize>,
+}
+
+/// Stop of the age gradient, e.g. `{ position = 0.5, color = "#ffd300" }`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GradientStop {
+    pub position: f32,
+    pub color: String,
+}
+
+impl Overrides {
+    /// Drop invalid overrides, so the defaults are used instead, and describe them in `warnings`.
+    fn validated(mut self, warnings: &mut Vec<String>) -> Self {
+        let mut invalid = |field: &str, reason: &str| {
+            warnings.push(format!("Ignoring `{field}` override: {reason}"));
+        };
+
+        if self.window.width == Some(0) {
+            invalid("window.width", "must be positive");
+            self.window.width = None;
+        }
+        if self.window.height == Some(0) {
+            invalid("window.height", "must be positive");
+            self.window.height = None;
+        }
+
+        if let Some(tps) = self.sim.ticks_per_second {
+            let range = sim::MIN_TICKS_PER_SECOND..=sim::MAX_TICKS_PER_SECOND;
+            if !range.contains(&tps) {
+                invalid(
+                    "sim.ticks_per_second",
+                    &format!("must be within {}..={}", range.start(), range.end()),
+                );
+                self.sim.ticks_per_second = None;
+            }
+        }
+
+        if let Some(size) = self.cells.sprite_size {
+            let width = self.window.width.unwrap_or(window::WIDTH);
+            let height = self.window.height.unwrap_or(window::HEIGHT);
+            let range = 2..=sim::MAX_BOARD_SIZE;
+
+            #[allow(
+                clippy::cast_precision_loss,
+                clippy::cast_possible_truncation,
+                clippy::cast_sign_loss
+            )]
+            let (columns, rows) = (
+                (width as f32 / size[0]) as u32,
+                (height as f32 / size[1]) as u32,
+            );
+
+            if size.iter().any(|side| !side.is_finite() || *side <= 0.0) {
+                invalid("cells.sprite_size", "sides must be positive");
+                self.cells.sprite_size = None;
+            } else if !range.contains(&columns) || !range.contains(&rows) {
+                invalid(
+                    "cells.sprite_size",
+                    &format!(
+                        "the window must fit {}..={} columns and rows",
+                        range.start(),
+                        range.end()
+                    ),
+                );
+                self.cells.sprite_size = None;
+            }
+        }
+
+        if let Some(stops) = &self.cells.age_gradient {
+            if stops.len() < 2 {
+                invalid("cells.age_gradient", "needs at least two stops");
+                self.cells.age_gradient = None;
+            } else if stops
+                .iter()
+                .any(|stop| !(0.0..=1.0).contains(&stop.position))
+            {
+                invalid("cells.age_gradient", "positions must be within 0.0..=1.0");
+                self.cells.age_gradient = None;
+            } else if let Some(stop) = stops
+                .iter()
+                .find(|stop| bevy::color::Srgba::hex(&stop.color).is_err())
+            {
+                invalid(
+                    "cells.age_gradient",
+                    &format!("invalid color `{}`", stop.color),
+                );
+                self.cells.age_gradient = None;
+            }
+        }
+
+        if self.editor.max_fill_cells == Some(0) {
+            invalid("editor.max_fill_cells", "must be positive");
+            self.editor.max_fill_cells = None;
+        }
+
+        self
+    }
+}
+
+
+static OVERRIDES: OnceLock<Overrides> = OnceLock::new();
+
+fn overrides() -> &'static Overrides {
+    OVERRIDES.get_or_init(Overrides::default)
+}
+
+
+/// Load the overrides from `path`, if present. Must be called before any of the defaults are read.
+///
+/// Problems with the overrides don't prevent startup. Returns warnings about them instead, to be
+/// logged once logging is set up.
+pub fn load_overrides(path: &Path) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    let overrides = match std::fs::read_to_string(path) {
+        Ok(input) => match toml::from_str::<Overrides>(&input) {
+            Ok(overrides) => overrides.validated(&mut warnings),
+            Err(err) => {
+                warnings.push(format!("Failed to parse '{}': {err}", path.display()));
+                Overrides::default()
+            }
+        },
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Overrides::default(),
+        Err(err) => {
+            warnings.push(format!("Failed to read '{}': {err}", path.display()));
+            Overrides::default()
+        }
+    };
+
+    if OVERRIDES.set(overrides).is_err() {
+        warnings.push(String::from("Overrides were read before they were loaded"));
+    }
+
+    warnings
+}
+
+
 pub mod window {
+    use std::time::Duration;
+
+    pub const TITLE: &str = "Conway's Game of Life";
+
+    /// Interval the window title is updated with the generation and population at.
+    pub const TITLE_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
     pub const WIDTH: u32 = 1280;
     pub const HEIGHT: u32 = 720;
+
+    pub fn width() -> u32 {
+        super::overrides().window.width.unwrap_or(WIDTH)
+    }
+
+    pub fn height() -> u32 {
+        super::overrides().window.height.unwrap_or(HEIGHT)
+    }
+}
+
+pub mod camera {
+    /// Zoom range of the camera, where 1.0 fits the window.
+    pub const MIN_ZOOM: f32 = 0.25;
+    pub const MAX_ZOOM: f32 = 8.0;
+
+    /// Zoom factor per mouse wheel line (notch), or per key press.
+    pub const ZOOM_STEP: f32 = 1.25;
+
+    /// Rate at which the camera catches up with the live cells when following them, per second.
+    pub const FOLLOW_RATE: f32 = 4.0;
+}
+
+pub mod fontsheet {
+    use bevy::math::UVec2;
+
+    /// CP437 fontsheet the cells are drawn with, relative to the `assets` folder.
+    pub const PATH: &str = "cp437_10x10.png";
+
+    /// Size of a single glyph in the fontsheet, in pixels.
+    pub const GLYPH_SIZE: UVec2 = UVec2::splat(10);
+    /// Number of glyphs in a row and column of the fontsheet.
+    pub const COLUMNS: u32 = 16;
+    pub const ROWS: u32 = 16;
 }
 
 pub mod cells {
-    use std::sync::LazyLock;
+    use std::time::Duration;
 
     use bevy::color::Srgba;
     use bevy::math::Vec2;
 
-    use crate::color_gradient::{ColorGradient, ColorPoint};
+    use crate::color_gradient::{ColorGradient, ColorPoint, Interpolation};
 
+    /// Size of a cell in world units. Sides don't have to be equal.
     pub const SPRITE_SIZE: Vec2 = Vec2::splat(20.0);
-    pub const SPRITE_WORLD_OFFSET: Vec2 = Vec2::new(10.0, 10.0);
+
+    pub fn sprite_size() -> Vec2 {
+        super::overrides()
+            .cells
+            .sprite_size
+            .map_or(SPRITE_SIZE, Vec2::from)
+    }
+
+    /// Offset of the sprites, so cells span from multiples of the sprite size.
+    pub fn sprite_world_offset() -> Vec2 {
+        sprite_size() / 2.0
+    }
+
+    /// Fontsheet glyphs live (and dying) cells and dead cells are drawn with.
+    pub const ALIVE_GLYPH: usize = 254;
+    /// Fontsheet glyph of the digit 0, followed by the digits 1 to 9.
+    pub const DIGIT_GLYPH: usize = 48;
+    pub const DEAD_GLYPH: usize = 255;
 
     pub const DEAD_COLOR: Srgba = bevy::color::palettes::css::GRAY;
 
-    pub fn get_age_color(q: f32) -> Srgba {
-        static GRADIENT: LazyLock<ColorGradient> = LazyLock::new(|| {
-            let mut gradient = ColorGradient::new();
-            gradient.insert(ColorPoint::new(0.0, Srgba::rgb_u8(143, 0, 255))); // violet (electric)
-            gradient.insert(ColorPoint::new(0.2, Srgba::rgb_u8(178, 34, 34))); // red (fire brick)
-            gradient.insert(ColorPoint::new(0.4, Srgba::rgb_u8(255, 121, 0))); // orange (safety)
-            gradient.insert(ColorPoint::new(0.6, Srgba::rgb_u8(255, 211, 0))); // yellow (ncs)
-            gradient.insert(ColorPoint::new(0.8, Srgba::rgb_u8(50, 205, 50))); // green (lime)
-            gradient.insert(ColorPoint::new(1.0, Srgba::rgb_u8(0, 183, 235))); // cyan (sub. primary)
+    /// Color of all live cells without age coloring, the violet the default age gradient starts
+    /// at.
+    pub const LIVE_COLOR: Srgba = Srgba::rgb(0.56, 0.0, 1.0);
+
+    /// Color of dying cells of "Generations" rules, fading to `DEAD_COLOR` as they decay.
+    pub const DYING_COLOR: Srgba = Srgba::rgb(0.2, 0.4, 1.0);
+
+    /// How long cells that die take to fade from their last color to `DEAD_COLOR`, regardless of
+    /// the tick rate.
+    pub const FADE_OUT_DURATION: Duration = Duration::from_millis(300);
+
+    /// Colors of live cells by their number of live neighbors, from 0 to 8. Under the standard
+    /// rule, cold colors die of loneliness, greens survive, and warm colors die of overcrowding.
+    pub const NEIGHBOR_COUNT_COLORS: [Srgba; 9] = [
+        Srgba::rgb(0.15, 0.2, 0.6),
+        Srgba::rgb(0.2, 0.45, 0.9),
+        Srgba::rgb(0.2, 0.75, 0.45),
+        Srgba::rgb(0.35, 0.95, 0.3),
+        Srgba::rgb(0.95, 0.85, 0.2),
+        Srgba::rgb(1.0, 0.6, 0.15),
+        Srgba::rgb(1.0, 0.35, 0.1),
+        Srgba::rgb(0.9, 0.15, 0.15),
+        Srgba::rgb(0.7, 0.05, 0.3),
+    ];
+
+    /// Colors of live cells of the "Immigration" rule variant.
+    pub const IMMIGRATION_COLORS: [Srgba; 2] = [
+        bevy::color::palettes::css::ORANGE_RED,
+        bevy::color::palettes::css::DEEP_SKY_BLUE,
+    ];
+
+    pub const GRID_COLOR: Srgba = Srgba::new(0.0, 0.0, 0.0, 0.5);
+
+    /// File the age gradient is saved to, relative to the working directory.
+    pub const GRADIENT_PATH: &str = "gradient.ron";
+
+    /// Number of precomputed samples of the age gradient.
+    pub const AGE_GRADIENT_STEPS: usize = 256;
+
+    /// Return the gradient cells are colored by according to their age, overridden by
+    /// `cells.age_gradient` if present.
+    pub fn age_gradient() -> ColorGradient {
+        let mut gradient = ColorGradient::new();
+        gradient.set_interpolation(Interpolation::Oklab);
+
+        if let Some(stops) = &super::overrides().cells.age_gradient {
+            for stop in stops {
+                // Colors are validated when loading the overrides.
+                if let Ok(color) = Srgba::hex(&stop.color) {
+                    gradient.insert(ColorPoint::new(stop.position, color));
+                }
+            }
+            return gradient;
+        }
+
+        gradient.insert(ColorPoint::new(0.0, Srgba::rgb_u8(143, 0, 255))); // violet (electric)
+        gradient.insert(ColorPoint::new(0.2, Srgba::rgb_u8(178, 34, 34))); // red (fire brick)
+        gradient.insert(ColorPoint::new(0.4, Srgba::rgb_u8(255, 121, 0))); // orange (safety)
+        gradient.insert(ColorPoint::new(0.6, Srgba::rgb_u8(255, 211, 0))); // yellow (ncs)
+        gradient.insert(ColorPoint::new(0.8, Srgba::rgb_u8(50, 205, 50))); // green (lime)
+        gradient.insert(ColorPoint::new(1.0, Srgba::rgb_u8(0, 183, 235))); // cyan (sub. primary)
+
+        gradient
+    }
+}
+
+pub mod editor {
+    use bevy::color::Srgba;
+
+    pub const SELECTION_COLOR: Srgba = Srgba::new(1.0, 1.0, 1.0, 0.8);
+    pub const PASTE_PREVIEW_COLOR: Srgba = Srgba::new(1.0, 1.0, 1.0, 0.5);
+    pub const LINE_PREVIEW_COLOR: Srgba = Srgba::new(1.0, 1.0, 1.0, 0.5);
+
+    /// Upper bound of the brush radius that can be set in the UI.
+    pub const MAX_BRUSH_RADIUS: u32 = 16;
+
+    /// Most cells the fill tool fills at once. Larger regions aren't filled at all, so filling an
+    /// open region by accident doesn't fill the whole world.
+    pub const MAX_FILL_CELLS: usize = 4096;
+
+    pub fn max_fill_cells() -> usize {
+        super::overrides()
+            .editor
+            .max_fill_cells
+            .unwrap_or(MAX_FILL_CELLS)
+    }
+}
+
+pub mod touch {
+    /// Distance in logical pixels a finger can move between touching and lifting, and still count
+    /// as tapping.
+    pub const TAP_DISTANCE: f32 = 10.0;
+}
+
+pub mod ui {
+    /// Interval in seconds the measured tick rate is averaged over.
+    pub const TPS_SAMPLE_INTERVAL: f32 = 1.0;
+}
+
+pub mod patterns {
+    /// Pattern file (RLE) that, if present in the working directory, is loaded at startup in
+    /// place of the built-in "Butterfly" pattern.
+    pub const STARTUP_PATTERN_PATH: &str = "startup.rle";
+}
+
+pub mod save {
+    /// File the simulation state is saved to, relative to the working directory.
+    pub const STATE_PATH: &str = "life.ron";
+    /// File the statistics of the recorded generations are exported to as CSV.
+    pub const STATS_PATH: &str = "stats.csv";
+}
+
+pub mod settings {
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    /// File the settings are saved to, in the folder of the game within the per-user config folder.
+    pub const FILE_NAME: &str = "settings.ron";
+    /// Name of the folder of the game within the per-user config folder.
+    pub const DIR_NAME: &str = "game-of-life";
+    /// Key of `localStorage` the settings are saved under on the web.
+    pub const STORAGE_KEY: &str = "game-of-life.settings";
 
-            gradient
-        });
+    /// Interval the settings are checked for changes, and saved if they changed, at.
+    pub const SAVE_INTERVAL: Duration = Duration::from_secs(2);
 
-        GRADIENT.sample(q)
+    /// Return the file the settings are saved to: in `%APPDATA%` on Windows,
+    /// `~/Library/Application Support` on macOS, and `$XDG_CONFIG_HOME` or `~/.config` elsewhere.
+    /// Returns `None` if the config folder is unknown.
+    pub fn path() -> Option<PathBuf> {
+        let env = |name| std::env::var_os(name).filter(|value| !value.is_empty());
+
+        let config_dir = if cfg!(target_os = "windows") {
+            env("APPDATA").map(PathBuf::from)
+        } else if cfg!(target_os = "macos") {
+            env("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+        } else {
+            env("XDG_CONFIG_HOME")
+                .map(PathBuf::from)
+                .or_else(|| env("HOME").map(|home| PathBuf::from(home).join(".config")))
+        }?;
+
+        Some(config_dir.join(DIR_NAME).join(FILE_NAME))
     }
 }
 
+pub mod screenshot {
+    /// Hide the UI while taking a screenshot, so the screenshot is just the board.
+    pub const HIDE_UI: bool = true;
+}
+
 pub mod sim {
     pub const DEFAULT_TICKS_PER_SECOND: i32 = 4;
+    /// Range of the tick rate, whether set with the keyboard, in the UI, or in `config.toml`.
+    pub const MIN_TICKS_PER_SECOND: i32 = 1;
+    pub const MAX_TICKS_PER_SECOND: i32 = 240;
+
+    pub fn default_ticks_per_second() -> i32 {
+        super::overrides()
+            .sim
+            .ticks_per_second
+            .unwrap_or(DEFAULT_TICKS_PER_SECOND)
+    }
+
+    /// Seed of the random number generator used to generate random soups.
+    pub const DEFAULT_SOUP_SEED: u64 = 0x5EED;
+    /// Probability of a cell being alive in a random soup.
+    pub const DEFAULT_SOUP_DENSITY: f32 = 0.25;
+
+    /// Number of past generations kept for rewinding.
+    pub const DEFAULT_HISTORY_LIMIT: usize = 32;
+    /// Upper bound of the history limit that can be set in the UI.
+    pub const MAX_HISTORY_LIMIT: usize = 4096;
+
+    /// Upper bound of the width and height of the world that can be set in the UI.
+    pub const MAX_BOARD_SIZE: u32 = 512;
+    /// Number of snapshots kept in memory. Taking another forgets the oldest.
+    pub const MAX_SNAPSHOTS: usize = 8;
+
+    /// Number of most recent generations whose statistics are kept for exporting them.
+    pub const POPULATION_HISTORY_LIMIT: usize = 100_000;
+
+    /// Number of most recent advances the average step time is measured over.
+    pub const STEP_TIME_SAMPLES: usize = 64;
+
+    /// Number of dead cells around the live cells of an unbounded world.
+    pub const UNBOUNDED_MARGIN: i32 = 16;
+
+    /// Upper bound of the number of generations that can be advanced at once in the UI.
+    pub const MAX_STEP_COUNT: u32 = 10_000;
+    /// Number of generations advanced per frame when running until a target generation as fast
+    /// as possible.
+    pub const MAX_STEPS_PER_FRAME: u32 = 100;
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::Overrides;
+
+    #[test]
+    pub fn test_overrides() {
+        let overrides: Overrides = toml::from_str(
+            "[window]\nwidth = 1920\nheight = 0\n\n[cells]\nage_gradient = [{ position = 0.0, \
+             color = \"#ff0000\" }]\n",
+        )
+        .unwrap();
+
+        let mut warnings = Vec::new();
+        let overrides = overrides.validated(&mut warnings);
+        assert_eq!(overrides.window.width, Some(1920));
+        assert_eq!(overrides.window.height, None);
+        assert_eq!(overrides.sim.ticks_per_second, None);
+        assert!(overrides.cells.age_gradient.is_none());
+        assert_eq!(warnings.len(), 2);
+
+        assert!(toml::from_str::<Overrides>("[window]\ncolor = 1\n").is_err());
+
+        // The default window is 1280 by 720 pixels.
+        for (sprite_size, valid) in [
+            ("[20.0, 20.0]", true),
+            ("[2.0, 2.0]", false),
+            ("[1000.0, 20.0]", false),
+            ("[0.0, 20.0]", false),
+        ] {
+            let overrides: Overrides =
+                toml::from_str(&format!("[cells]\nsprite_size = {sprite_size}\n")).unwrap();
+            let overrides = overrides.validated(&mut warnings);
+            assert_eq!(
+                overrides.cells.sprite_size.is_some(),
+                valid,
+                "{sprite_size}"
+            );
+        }
+    }
 }
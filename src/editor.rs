@@ -0,0 +1,347 @@
+//
+// Copyright (c) 2023 Martin Green. All rights reserved.
+//
+
+use bevy::math::IRect;
+use bevy::prelude::*;
+
+use crate::game::{Cell, GameLogicSet, Life};
+use crate::input::{ctrl_pressed, line_cells, CursorWorldPosition, InputAction, LineStart};
+use crate::{config, patterns};
+
+
+/// Selected rectangular region of cells. Both corners are inclusive.
+#[derive(Default, Resource, Deref)]
+pub struct Selection(Option<IRect>);
+
+
+/// Live cells copied from the selection, relative to the minimum corner of the selection.
+#[derive(Default, Resource, Deref, DerefMut)]
+pub struct Clipboard(pub Vec<IVec2>);
+
+impl Clipboard {
+    /// Rotate the cells 90° counterclockwise around the center of their bounding box.
+    pub fn rotate(&mut self) {
+        self.transform(|xy| IVec2::new(-xy.y, xy.x));
+    }
+
+    /// Mirror the cells across the vertical (`horizontal`) or horizontal axis through the center
+    /// of their bounding box.
+    pub fn flip(&mut self, horizontal: bool) {
+        if horizontal {
+            self.transform(|xy| IVec2::new(-xy.x, xy.y));
+        } else {
+            self.transform(|xy| IVec2::new(xy.x, -xy.y));
+        }
+    }
+
+    /// Apply a linear transform to the cells, pivoting around the center of their bounding box.
+    /// The transform is given doubled offsets from the center.
+    fn transform(&mut self, f: impl Fn(IVec2) -> IVec2) {
+        let Some((min, max)) = self.iter().fold(None, |extent, xy| match extent {
+            None => Some((*xy, *xy)),
+            Some((min, max)) => Some((xy.min(min), xy.max(max))),
+        }) else {
+            return;
+        };
+
+        // Doubled coordinates, so half-integer centers are exact.
+        let center2 = min + max;
+        for xy in &mut self.0 {
+            let offset2 = f(*xy * 2 - center2);
+            // Odd sizes offset all cells alike, so rounding down keeps the shape intact.
+            *xy = IVec2::new(
+                (offset2.x + center2.x).div_euclid(2),
+                (offset2.y + center2.y).div_euclid(2),
+            );
+        }
+    }
+}
+
+
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Selection>()
+            .init_resource::<Clipboard>()
+            .add_systems(
+                Update,
+                (
+                    select_region,
+                    copy_selection.after(select_region),
+                    copy_pattern,
+                    transform_clipboard
+                        .after(copy_selection)
+                        .after(copy_pattern),
+                    paste.after(transform_clipboard),
+                    flood_fill,
+                )
+                    .in_set(GameLogicSet),
+            )
+            .add_systems(
+                Update,
+                (draw_selection, draw_paste_preview, draw_line_preview).after(GameLogicSet),
+            );
+    }
+}
+
+
+fn select_region(
+    mut selection: ResMut<'_, Selection>,
+    mut actions: EventReader<'_, '_, InputAction>,
+) {
+    for action in actions.read() {
+        if let InputAction::Select(region) = action {
+            selection.0 = Some(*region);
+        }
+    }
+}
+
+
+/// Copy the live cells within the selection to the clipboard.
+fn copy_selection(
+    life: Res<'_, Life>,
+    selection: Res<'_, Selection>,
+    mut clipboard: ResMut<'_, Clipboard>,
+    mut actions: EventReader<'_, '_, InputAction>,
+) {
+    for action in actions.read() {
+        if let InputAction::CopySelection = action {
+            let Some(region) = **selection else {
+                info!("Nothing is selected");
+                continue;
+            };
+
+            clipboard.0 = life
+                .cells
+                .iter()
+                .filter(|(xy, cell)| {
+                    cell.alive && xy.cmpge(region.min).all() && xy.cmple(region.max).all()
+                })
+                .map(|(xy, _)| *xy - region.min)
+                .collect();
+            debug!("Copied {} cells", clipboard.len());
+        }
+    }
+}
+
+
+/// Copy a pattern in the RLE format to the clipboard.
+fn copy_pattern(
+    mut clipboard: ResMut<'_, Clipboard>,
+    mut actions: EventReader<'_, '_, InputAction>,
+) {
+    for action in actions.read() {
+        if let InputAction::CopyPattern(rle) = action {
+            let cells = match patterns::parse_rle(rle) {
+                Ok(cells) => cells,
+                Err(err) => {
+                    error!("Failed to parse pattern: {err}");
+                    continue;
+                }
+            };
+
+            let min = cells.iter().fold(IVec2::MAX, |min, (xy, _)| min.min(*xy));
+            clipboard.0 = cells.into_iter().map(|(xy, _)| xy - min).collect();
+            debug!("Copied {} cells", clipboard.len());
+        }
+    }
+}
+
+
+fn transform_clipboard(
+    mut clipboard: ResMut<'_, Clipboard>,
+    mut actions: EventReader<'_, '_, InputAction>,
+) {
+    for action in actions.read() {
+        match action {
+            InputAction::RotateClipboard => clipboard.rotate(),
+            InputAction::FlipClipboard { horizontal } => clipboard.flip(*horizontal),
+            _ => {}
+        }
+    }
+}
+
+
+/// Stamp the clipboard onto the board. Cells outside of the world bounds are clipped.
+fn paste(
+    mut life: ResMut<'_, Life>,
+    clipboard: Res<'_, Clipboard>,
+    mut actions: EventReader<'_, '_, InputAction>,
+) {
+    for action in actions.read() {
+        if let InputAction::Paste(origin) = action {
+            if clipboard.is_empty() {
+                info!("Clipboard is empty");
+                continue;
+            }
+
+            life.insert_pattern(clipboard.iter().map(|xy| (*xy + *origin, Cell::default())));
+            life.redo.clear();
+            life.update_max_age();
+            debug!("Pasted {} cells at {origin}", clipboard.len());
+        }
+    }
+}
+
+
+/// Set the empty region around a cell alive, unless it has more than
+/// `config::editor::max_fill_cells()` cells.
+fn flood_fill(mut life: ResMut<'_, Life>, mut actions: EventReader<'_, '_, InputAction>) {
+    for action in actions.read() {
+        if let InputAction::FloodFill(start) = action {
+            let limit = config::editor::max_fill_cells();
+            let Some(region) = life.flood_fill(*start, limit) else {
+                info!("Not filling {start}: not empty, or more than {limit} cells");
+                continue;
+            };
+
+            life.insert_pattern(region.iter().map(|xy| (*xy, Cell::default())));
+            life.redo.clear();
+            life.update_max_age();
+            debug!("Filled {} cells", region.len());
+        }
+    }
+}
+
+
+/// Draw the outline of the selection along the cell edges.
+fn draw_selection(selection: Res<'_, Selection>, mut gizmos: Gizmos<'_, '_>) {
+    let sprite_size = config::cells::sprite_size();
+
+    let Some(region) = **selection else {
+        return;
+    };
+
+    let min = region.min.as_vec2() * sprite_size;
+    let max = (region.max + IVec2::ONE).as_vec2() * sprite_size;
+
+    draw_rect(&mut gizmos, min, max, config::editor::SELECTION_COLOR);
+}
+
+
+/// Draw the cells of the clipboard where they would be pasted while `Ctrl` is held down.
+fn draw_paste_preview(
+    keys: Res<'_, ButtonInput<KeyCode>>,
+    mouse_position: Res<'_, CursorWorldPosition>,
+    clipboard: Res<'_, Clipboard>,
+    mut gizmos: Gizmos<'_, '_>,
+) {
+    let sprite_size = config::cells::sprite_size();
+
+    if clipboard.is_empty() || !ctrl_pressed(&keys) {
+        return;
+    }
+
+    let origin = mouse_position.cell();
+    for xy in clipboard.iter() {
+        let min = (*xy + origin).as_vec2() * sprite_size;
+        let inset = sprite_size * 0.1;
+        draw_rect(
+            &mut gizmos,
+            min + inset,
+            min + sprite_size - inset,
+            config::editor::PASTE_PREVIEW_COLOR,
+        );
+    }
+}
+
+
+/// Draw the cells of the line being drawn with the line tool, up to the cell under the cursor.
+fn draw_line_preview(
+    mouse_position: Res<'_, CursorWorldPosition>,
+    start: Res<'_, LineStart>,
+    mut gizmos: Gizmos<'_, '_>,
+) {
+    let sprite_size = config::cells::sprite_size();
+
+    let Some(from) = **start else {
+        return;
+    };
+
+    for xy in line_cells(from, mouse_position.cell()) {
+        let min = xy.as_vec2() * sprite_size;
+        let inset = sprite_size * 0.1;
+        draw_rect(
+            &mut gizmos,
+            min + inset,
+            min + sprite_size - inset,
+            config::editor::LINE_PREVIEW_COLOR,
+        );
+    }
+}
+
+
+/// Draw the outline of an axis-aligned rectangle.
+fn draw_rect(gizmos: &mut Gizmos<'_, '_>, min: Vec2, max: Vec2, color: Srgba) {
+    gizmos.linestrip_2d(
+        [
+            min,
+            Vec2::new(max.x, min.y),
+            max,
+            Vec2::new(min.x, max.y),
+            min,
+        ],
+        color,
+    );
+}
+
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use super::Clipboard;
+
+    fn sorted(clipboard: &Clipboard) -> Vec<IVec2> {
+        let mut cells = clipboard.0.clone();
+        cells.sort_by_key(|xy| (xy.x, xy.y));
+
+        cells
+    }
+
+    #[test]
+    pub fn test_rotate_clipboard() {
+        // Horizontal blinker within a 3x3 bounding box.
+        let mut clipboard = Clipboard([(0, 1), (1, 1), (2, 1)].map(IVec2::from).to_vec());
+        clipboard.rotate();
+        assert_eq!(
+            sorted(&clipboard),
+            [(1, 0), (1, 1), (1, 2)].map(IVec2::from)
+        );
+
+        // Four rotations restore the original cells.
+        let glider = [(1, 2), (2, 1), (0, 0), (1, 0), (2, 0)].map(IVec2::from);
+        let mut clipboard = Clipboard(glider.to_vec());
+        for _ in 0..4 {
+            clipboard.rotate();
+        }
+        assert_eq!(sorted(&clipboard), sorted(&Clipboard(glider.to_vec())));
+
+        // An even-sized footprint stays roughly in place.
+        let mut clipboard = Clipboard([(0, 0), (1, 0), (2, 0), (3, 0)].map(IVec2::from).to_vec());
+        clipboard.rotate();
+        let cells = sorted(&clipboard);
+        assert!(cells.iter().all(|xy| xy.x == cells[0].x));
+        assert_eq!(cells.first().unwrap().y, -2);
+        assert_eq!(cells.last().unwrap().y, 1);
+    }
+
+    #[test]
+    pub fn test_flip_clipboard() {
+        let mut clipboard = Clipboard([(0, 0), (1, 0), (0, 1)].map(IVec2::from).to_vec());
+
+        clipboard.flip(true);
+        assert_eq!(
+            sorted(&clipboard),
+            [(0, 0), (1, 0), (1, 1)].map(IVec2::from)
+        );
+
+        clipboard.flip(false);
+        assert_eq!(
+            sorted(&clipboard),
+            [(0, 1), (1, 0), (1, 1)].map(IVec2::from)
+        );
+    }
+}
@@ -3,53 +3,217 @@
 //
 
 use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use ahash::AHashMap as HashMap;
+use ahash::{AHashMap as HashMap, AHashSet as HashSet};
 use bevy::math::IRect;
 use bevy::prelude::*;
+use bevy::utils::Instant;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
 
 use crate::input::InputAction;
-use crate::{config, AppState};
+use crate::ui::ShowError;
+use crate::{config, patterns, AppState};
 
 
 #[derive(Clone, PartialEq, Eq, Hash, Debug, SystemSet)]
 pub struct GameLogicSet;
 
 
-pub struct GamePlugin;
+/// Sent when the world bounds may have changed, e.g. after resizing the board or loading a saved
+/// state, so anything laid out per cell can be rebuilt.
+#[derive(Event)]
+pub struct GridChanged;
+
+
+/// Sent when the last live cell dies while advancing the simulation. Dying cells of "Generations"
+/// rules may still be fading out.
+#[derive(Event)]
+pub struct BoardExtinct {
+    /// Generation the board went extinct at.
+    pub generation: u32,
+}
+
+
+/// Sent for every generation the simulation advances, with the cells born and died, e.g. to record
+/// the simulation or stream it elsewhere. Both lists are in no particular order.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Event)]
+pub struct GenerationDelta {
+    /// Cells that came alive.
+    pub born: Vec<IVec2>,
+    /// Cells that stopped being alive, including cells that started dying.
+    pub died: Vec<IVec2>,
+    /// Generation the simulation advanced to.
+    pub generation: u32,
+}
+
+impl GenerationDelta {
+    pub fn stats(&self) -> GenerationStats {
+        GenerationStats {
+            births: self.born.len(),
+            deaths: self.died.len(),
+        }
+    }
+}
+
+
+/// Pattern the simulation starts with.
+#[derive(Clone, Debug, Default, PartialEq, Resource)]
+pub enum InitialPattern {
+    Empty,
+    /// `config::patterns::STARTUP_PATTERN_PATH` if present, or else the "Butterfly" pattern, as
+    /// the game always started.
+    #[default]
+    Butterfly,
+    /// Random soup of `config::sim::DEFAULT_SOUP_DENSITY` from the current soup seed.
+    Random,
+    /// Pattern of `patterns::LIBRARY`, by name.
+    Preset(&'static str),
+    /// Pattern in the RLE format loaded from a file.
+    File(PathBuf),
+    /// Live cells given by position.
+    Cells(Vec<IVec2>),
+}
+
+impl InitialPattern {
+    /// Return the cells of the pattern. Patterns that can't be loaded fall back to the "Butterfly"
+    /// pattern, logging why.
+    pub fn cells(&self, bounds: IRect, seed: u64, variant: RuleVariant) -> Vec<(IVec2, Cell)> {
+        let butterfly =
+            || patterns::parse_rle(patterns::BUTTERFLY).expect("built-in pattern should be valid");
+
+        match self {
+            Self::Empty => Vec::new(),
+            Self::Butterfly => load_startup_pattern().unwrap_or_else(butterfly),
+            Self::Random => random_soup(bounds, seed, config::sim::DEFAULT_SOUP_DENSITY, variant)
+                .into_iter()
+                .collect(),
+            Self::Preset(name) => {
+                let Some((_, rle)) = patterns::LIBRARY.iter().find(|(preset, _)| preset == name)
+                else {
+                    warn!("No pattern named '{name}'");
+                    return butterfly();
+                };
+                patterns::parse_rle(rle).unwrap_or_else(|err| {
+                    error!("Failed to parse pattern '{name}': {err}");
+                    butterfly()
+                })
+            }
+            Self::File(path) => load_pattern_file(path).unwrap_or_else(butterfly),
+            Self::Cells(cells) => cells.iter().map(|xy| (*xy, Cell::default())).collect(),
+        }
+    }
+
+    /// Return a short description of the pattern for the UI.
+    pub fn label(&self) -> String {
+        match self {
+            Self::Empty => String::from("Empty"),
+            Self::Butterfly => String::from("Butterfly"),
+            Self::Random => String::from("Random soup"),
+            Self::Preset(name) => String::from(*name),
+            Self::File(path) => format!("File '{}'", path.display()),
+            Self::Cells(cells) => format!("{} cells", cells.len()),
+        }
+    }
+}
+
+
+/// The simulation and its systems. The default options start the game as it always has.
+pub struct GamePlugin {
+    pub initial_pattern: InitialPattern,
+    pub ticks_per_second: i32,
+    pub rule: Rule,
+}
+
+impl Default for GamePlugin {
+    fn default() -> Self {
+        Self {
+            initial_pattern: InitialPattern::default(),
+            ticks_per_second: config::sim::default_ticks_per_second(),
+            rule: Rule::default(),
+        }
+    }
+}
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
-        let tps = config::sim::DEFAULT_TICKS_PER_SECOND;
-
-        #[allow(clippy::cast_precision_loss)]
-        app.insert_resource(SimulationConfig {
+        let tps = self.ticks_per_second;
+        let config = SimulationConfig {
             ticks_per_second: tps,
-        })
-        .insert_resource(SimulationUpdateTimer(Timer::from_seconds(
-            1.0 / tps as f32,
-            TimerMode::Repeating,
-        )))
-        .configure_sets(OnEnter(AppState::Running), GameLogicSet)
-        .configure_sets(Update, GameLogicSet.run_if(on_event::<InputAction>()))
-        .add_systems(
-            OnEnter(AppState::Running),
-            setup_simulation.in_set(GameLogicSet).run_if(run_once()),
-        )
-        .add_systems(
-            Update,
-            (advance_simulation, rewind_simulation, toggle_cell).in_set(GameLogicSet),
-        )
-        .add_systems(
-            Update,
-            tick_simulation_update_timer.run_if(in_state(AppState::Running)),
-        )
-        .add_systems(OnEnter(AppState::Paused), reset_simulation_update_timer);
+            rule: self.rule,
+            ..default()
+        };
+
+        app.insert_resource(config)
+            .insert_resource(self.initial_pattern.clone())
+            .add_event::<GridChanged>()
+            .add_event::<BoardExtinct>()
+            .add_event::<GenerationDelta>()
+            .init_resource::<DetectedPeriod>()
+            .init_resource::<GenerationStats>()
+            .init_resource::<StepTimes>()
+            .init_resource::<PopulationHistory>()
+            .init_resource::<Snapshots>()
+            .init_resource::<SoupSeed>()
+            .init_resource::<RunTarget>()
+            .insert_resource(SimulationUpdateTimer::new(tps))
+            .configure_sets(OnEnter(AppState::Running), GameLogicSet)
+            .configure_sets(Update, GameLogicSet.run_if(on_event::<InputAction>()))
+            .add_systems(
+                OnEnter(AppState::Running),
+                setup_simulation.in_set(GameLogicSet).run_if(run_once()),
+            )
+            .add_systems(
+                Update,
+                (
+                    advance_simulation,
+                    rewind_simulation,
+                    redo_simulation,
+                    toggle_cell,
+                    randomize_board,
+                    clear_board,
+                    reset_simulation,
+                    reset_generation,
+                    save_state,
+                    export_stats.after(advance_simulation),
+                    load_state,
+                    resize_board,
+                    snapshot_board,
+                    detect_period
+                        .after(advance_simulation)
+                        .after(rewind_simulation)
+                        .after(redo_simulation)
+                        .after(toggle_cell)
+                        .after(randomize_board)
+                        .after(clear_board)
+                        .after(reset_simulation)
+                        .after(reset_generation)
+                        .after(load_state)
+                        .after(resize_board)
+                        .after(snapshot_board),
+                )
+                    .in_set(GameLogicSet),
+            )
+            .add_systems(
+                Update,
+                (
+                    tick_simulation_update_timer.run_if(in_state(AppState::Running)),
+                    run_until_target.after(GameLogicSet),
+                    pause_on_extinction
+                        .after(GameLogicSet)
+                        .run_if(on_event::<BoardExtinct>()),
+                ),
+            )
+            .add_systems(OnEnter(AppState::Paused), reset_simulation_update_timer);
     }
 }
 
 
-const NEIGHBOR_OFFSETS: [IVec2; 8] = [
+const MOORE_NEIGHBOR_OFFSETS: [IVec2; 8] = [
     IVec2 { x: 0, y: 1 },
     IVec2 { x: 1, y: 1 },
     IVec2 { x: 1, y: 0 },
@@ -60,239 +224,2280 @@ const NEIGHBOR_OFFSETS: [IVec2; 8] = [
     IVec2 { x: -1, y: 1 },
 ];
 
+const VON_NEUMANN_NEIGHBOR_OFFSETS: [IVec2; 4] = [
+    IVec2 { x: 0, y: 1 },
+    IVec2 { x: 1, y: 0 },
+    IVec2 { x: 0, y: -1 },
+    IVec2 { x: -1, y: 0 },
+];
+
 
 #[derive(Resource)]
 pub struct SimulationConfig {
     pub ticks_per_second: i32,
+    /// Tick every frame, as fast as possible, regardless of `ticks_per_second`.
+    pub unlimited: bool,
+    /// Rewind rather than advance the simulation on every tick, until the history is exhausted.
+    pub reverse: bool,
+    pub rule: Rule,
+    pub variant: RuleVariant,
+    pub neighborhood: Neighborhood,
+    pub topology: Topology,
+    /// Pause the simulation once it reaches a still life.
+    pub detect_stable: bool,
+    /// Pause the simulation once it reaches an oscillator of a period within the history.
+    pub detect_oscillation: bool,
+    /// Pause the simulation once every cell has died.
+    pub detect_extinction: bool,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            ticks_per_second: config::sim::default_ticks_per_second(),
+            unlimited: false,
+            reverse: false,
+            rule: Rule::default(),
+            variant: RuleVariant::default(),
+            neighborhood: Neighborhood::default(),
+            topology: Topology::default(),
+            detect_stable: true,
+            detect_oscillation: false,
+            detect_extinction: false,
+        }
+    }
 }
 
 
-#[derive(Resource, Deref, DerefMut)]
-pub struct SimulationUpdateTimer(pub Timer);
+/// Period of the current generation, i.e. the distance to the most recent identical generation in
+/// the history. A period of 1 is a still life.
+#[derive(Default, Resource, Deref)]
+pub struct DetectedPeriod(pub Option<usize>);
 
 
-#[derive(Copy, Clone)]
-pub struct Cell {
-    pub alive: bool,
-    pub age: u32,
+/// Cells born and died in the most recent advance of the simulation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Resource)]
+pub struct GenerationStats {
+    pub births: usize,
+    pub deaths: usize,
 }
 
-impl Cell {
-    fn new(alive: bool, age: u32) -> Self {
-        Self { alive, age }
+
+/// Statistics of a single generation, as recorded in [`PopulationHistory`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GenerationRecord {
+    pub generation: u32,
+    pub population: usize,
+    pub births: usize,
+    pub deaths: usize,
+}
+
+
+/// Statistics of the most recently advanced generations, oldest first, for exporting them.
+#[derive(Default, Resource)]
+pub struct PopulationHistory(VecDeque<GenerationRecord>);
+
+impl PopulationHistory {
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Record the statistics of a generation, forgetting the oldest beyond
+    /// `config::sim::POPULATION_HISTORY_LIMIT`. Records of the same or later generations, left
+    /// over from before rewinding or resetting the generation, are forgotten too.
+    pub fn push(&mut self, record: GenerationRecord) {
+        while self
+            .0
+            .back()
+            .is_some_and(|last| last.generation >= record.generation)
+        {
+            self.0.pop_back();
+        }
+        if self.0.len() >= config::sim::POPULATION_HISTORY_LIMIT {
+            self.0.pop_front();
+        }
+        self.0.push_back(record);
+    }
+
+    /// Write the records as CSV, with a header row.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing to `writer` fails.
+    pub fn write_csv(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "generation,population,births,deaths")?;
+        for record in &self.0 {
+            writeln!(
+                writer,
+                "{},{},{},{}",
+                record.generation, record.population, record.births, record.deaths
+            )?;
+        }
+
+        writer.flush()
+    }
+
+    /// Write the records as CSV to `path`. The records are written to a temporary file next to
+    /// `path` first, so a failed export doesn't leave a partial file behind.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be written.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_csv(&self, path: &Path) -> io::Result<()> {
+        let temp_path = path.with_extension("csv.tmp");
+        let result = std::fs::File::create(&temp_path)
+            .and_then(|file| self.write_csv(io::BufWriter::new(file)))
+            .and_then(|()| std::fs::rename(&temp_path, path));
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temp_path);
+        }
+
+        result
+    }
+
+    /// Offer the records as CSV for download, named after the file name of `path`, as there is no
+    /// filesystem on the web.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the browser refuses to create the download.
+    #[cfg(target_arch = "wasm32")]
+    pub fn save_csv(&self, path: &Path) -> io::Result<()> {
+        use wasm_bindgen::{JsCast, JsValue};
+
+        let js_error = |err: JsValue| io::Error::other(format!("{err:?}"));
+
+        let mut csv = Vec::new();
+        self.write_csv(&mut csv)?;
+        let csv = String::from_utf8(csv).map_err(io::Error::other)?;
+
+        let options = web_sys::BlobPropertyBag::new();
+        options.set_type("text/csv");
+        let blob = web_sys::Blob::new_with_str_sequence_and_options(
+            &js_sys::Array::of1(&JsValue::from_str(&csv)),
+            &options,
+        )
+        .map_err(js_error)?;
+        let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(js_error)?;
+
+        let anchor = web_sys::window()
+            .and_then(|window| window.document())
+            .ok_or_else(|| io::Error::other("`document` is unavailable"))?
+            .create_element("a")
+            .map_err(js_error)?
+            .dyn_into::<web_sys::HtmlAnchorElement>()
+            .map_err(|_| io::Error::other("`a` isn't an anchor element"))?;
+        anchor.set_href(&url);
+        anchor.set_download(&path.file_name().unwrap_or_default().to_string_lossy());
+        anchor.click();
+
+        web_sys::Url::revoke_object_url(&url).map_err(js_error)
     }
 }
 
-impl Default for Cell {
-    fn default() -> Self {
-        Self::new(true, 0)
+
+/// Durations of the most recent advances of the simulation, for measuring its performance.
+#[derive(Default, Resource)]
+pub struct StepTimes(VecDeque<Duration>);
+
+impl StepTimes {
+    /// Record the duration of an advance, forgetting the oldest beyond
+    /// `config::sim::STEP_TIME_SAMPLES`.
+    pub fn push(&mut self, duration: Duration) {
+        if self.0.len() >= config::sim::STEP_TIME_SAMPLES {
+            self.0.pop_back();
+        }
+        self.0.push_front(duration);
+    }
+
+    /// Return the duration of the most recent advance.
+    pub fn last(&self) -> Option<Duration> {
+        self.0.front().copied()
+    }
+
+    /// Return the average duration of the recorded advances.
+    pub fn average(&self) -> Option<Duration> {
+        let count = u32::try_from(self.0.len())
+            .ok()
+            .filter(|count| *count > 0)?;
+        Some(self.0.iter().sum::<Duration>() / count)
     }
 }
 
 
-#[derive(Resource)]
-pub struct Life {
-    pub bounds: IRect,
-    pub history: VecDeque<HashMap<IVec2, Cell>>,
-    pub cells: HashMap<IVec2, Cell>,
-    pub generation: u32,
-    pub max_age: u32,
+/// Boards kept in memory for comparing them quickly, without saving them.
+#[derive(Default, Resource)]
+pub struct Snapshots {
+    boards: Vec<HashMap<IVec2, Cell>>,
+    /// Index of the most recently taken or loaded snapshot.
+    current: Option<usize>,
+    /// Restart at generation 0 when loading a snapshot, rather than keeping the generation.
+    pub reset_generation: bool,
 }
 
-impl Life {
-    pub const MAX_HISTORY_SIZE: usize = 32;
+impl Snapshots {
+    pub fn len(&self) -> usize {
+        self.boards.len()
+    }
 
-    #[allow(clippy::cast_possible_wrap)]
-    pub fn new(width: u32, height: u32) -> Self {
-        let half_width = (width / 2) as i32;
-        let half_height = (height / 2) as i32;
+    pub fn is_empty(&self) -> bool {
+        self.boards.is_empty()
+    }
 
-        let max = IVec2 {
-            x: half_width,
-            y: half_height,
-        };
-        let min = -max;
+    /// Return the index of the most recently taken or loaded snapshot.
+    pub fn current(&self) -> Option<usize> {
+        self.current
+    }
 
-        Self {
-            bounds: IRect::from_corners(min, max),
-            cells: HashMap::new(),
-            history: VecDeque::with_capacity(Self::MAX_HISTORY_SIZE),
-            generation: 0,
-            max_age: 0,
+    /// Keep `cells` as the latest snapshot, forgetting the oldest beyond
+    /// `config::sim::MAX_SNAPSHOTS`.
+    pub fn push(&mut self, cells: HashMap<IVec2, Cell>) {
+        if self.boards.len() >= config::sim::MAX_SNAPSHOTS {
+            self.boards.remove(0);
+        }
+        self.boards.push(cells);
+        self.current = Some(self.boards.len() - 1);
+    }
+
+    /// Move on to the next or previous snapshot, wrapping around, and return it.
+    pub fn cycle(&mut self, next: bool) -> Option<&HashMap<IVec2, Cell>> {
+        let len = self.boards.len();
+        if len == 0 {
+            return None;
         }
+
+        let current = match self.current {
+            Some(current) if next => (current + 1) % len,
+            Some(current) => (current + len - 1) % len,
+            None => len - 1,
+        };
+        self.current = Some(current);
+
+        self.boards.get(current)
     }
 }
 
 
-fn setup_simulation(mut life: ResMut<'_, Life>) {
-    // "Butterfly" pattern.
-    life.cells.insert(IVec2::new(0, 3), Cell::default());
-    life.cells.insert(IVec2::new(0, 2), Cell::default());
-    life.cells.insert(IVec2::new(0, 1), Cell::default());
-    life.cells.insert(IVec2::new(0, 0), Cell::default());
-    life.cells.insert(IVec2::new(0, -1), Cell::default());
-    life.cells.insert(IVec2::new(0, -2), Cell::default());
-    life.cells.insert(IVec2::new(0, -3), Cell::default());
+/// Generation to advance the simulation to, after which it is paused.
+#[derive(Default, Resource)]
+pub struct RunTarget {
+    pub generation: Option<u32>,
+    /// Advance as fast as possible rather than at the tick rate.
+    pub fast: bool,
+}
+
 
-    life.cells.insert(IVec2::new(1, 0), Cell::default());
-    life.cells.insert(IVec2::new(-1, 0), Cell::default());
+/// Cells counted as neighbors of a cell.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Neighborhood {
+    /// The eight orthogonally and diagonally adjacent cells.
+    #[default]
+    Moore,
+    /// The four orthogonally adjacent cells.
+    VonNeumann,
 }
 
-fn tick_simulation_update_timer(
-    mut timer: ResMut<'_, SimulationUpdateTimer>,
-    time: Res<'_, Time>,
-    mut actions: EventWriter<'_, InputAction>,
-) {
-    if timer.tick(time.delta()).finished() {
-        // @REVIEW: **Technically** not an *input* action.
-        actions.send(InputAction::AdvanceSimulation);
+impl Neighborhood {
+    /// Return the offsets of the neighbors relative to the inner cell.
+    pub fn offsets(self) -> &'static [IVec2] {
+        match self {
+            Self::Moore => &MOORE_NEIGHBOR_OFFSETS,
+            Self::VonNeumann => &VON_NEUMANN_NEIGHBOR_OFFSETS,
+        }
     }
 }
 
 
-/// Reset simulation update timer.
+/// Variant of the rule, changing what live cells carry from generation to generation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RuleVariant {
+    /// All live cells are alike.
+    #[default]
+    Standard,
+    /// "Immigration", where live cells have one of two colors and cells are born with the
+    /// majority color of their live neighbors.
+    Immigration,
+}
+
+
+/// Color of a live cell of the "Immigration" rule variant.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImmigrationColor {
+    #[default]
+    A,
+    B,
+}
+
+
+/// Behavior of the world at its bounds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Topology {
+    /// Opposite edges are joined, i.e. cells leaving the world on one side reappear on the
+    /// opposite side.
+    #[default]
+    Torus,
+    /// Cells outside of the world bounds are permanently dead.
+    Wall,
+    /// There are no edges. The world bounds follow the live cells as they spread or move, see
+    /// [`Life::fit_bounds`].
+    Unbounded,
+}
+
+
+/// Life-like cellular automaton rule in the B/S notation, e.g. "B3/S23", optionally extended with
+/// the number of cell states of "Generations" rules, e.g. "B2/S/C3" for Brian's Brain.
 ///
-/// Executed on entering the `GameState::Paused` state.
-pub fn reset_simulation_update_timer(mut timer: ResMut<'_, SimulationUpdateTimer>) {
-    timer.reset();
+/// Both arrays are indexed by the number of live neighbors of a cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Rule {
+    /// Dead cells with this many live neighbors are born.
+    pub birth: [bool; 9],
+    /// Live cells with this many live neighbors survive.
+    pub survival: [bool; 9],
+    /// Number of states a dying cell counts down through. Cells that die enter state
+    /// `decay_states - 1`, count down a state every tick, and vanish at 0. Dying cells neither
+    /// count as live neighbors nor can be born again. 1 is a Life-like rule, where cells vanish
+    /// as soon as they die.
+    pub decay_states: u8,
 }
 
+impl Rule {
+    /// Well-known rules by name, in the B/S notation.
+    pub const PRESETS: [(&'static str, &'static str); 5] = [
+        ("Conway", "B3/S23"),
+        ("HighLife", "B36/S23"),
+        ("Day & Night", "B3678/S34678"),
+        ("Seeds", "B2/S"),
+        ("Replicator", "B1357/S1357"),
+    ];
+
+    /// Parse a rule in the B/S notation, e.g. "B3/S23" or "B36/S23". Letters are case-insensitive
+    /// and the birth and survival conditions may appear in either order. An optional "C"
+    /// condition gives the total number of cell states of a "Generations" rule, e.g. "B2/S/C3".
+    /// The numeric "S/B/C" notation of "Generations" rules, e.g. "/2/3", is accepted as well.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either condition is missing, contains anything besides digits 0-8, or
+    /// contains a digit twice, if cells are born without neighbors ("B0"), or if the number of
+    /// states is less than 2.
+    pub fn parse(rule: &str) -> Result<Self, RuleError> {
+        fn neighbor_counts(counts: &str) -> Result<[bool; 9], RuleError> {
+            let mut neighbors = [false; 9];
+            for c in counts.chars() {
+                match c.to_digit(10) {
+                    Some(n @ 0..=8) if neighbors[n as usize] => {
+                        return Err(RuleError::DuplicateNeighborCount(c));
+                    }
+                    Some(n @ 0..=8) => neighbors[n as usize] = true,
+                    _ => return Err(RuleError::InvalidNeighborCount(c)),
+                }
+            }
+
+            Ok(neighbors)
+        }
+
+        // Only cells next to live cells are considered for birth, see `Life::step`.
+        fn births(counts: &str) -> Result<[bool; 9], RuleError> {
+            let births = neighbor_counts(counts)?;
+            if births[0] {
+                return Err(RuleError::BirthWithoutNeighbors);
+            }
+
+            Ok(births)
+        }
 
-/// Advance the simulation a single tick (generation).
-pub fn advance_simulation(life: ResMut<'_, Life>, mut actions: EventReader<'_, '_, InputAction>) {
-    /// Wrap:
-    /// ```
-    /// max_x -> min_x
-    /// min_x - 1 -> max_x - 1
-    /// max_y -> min_y
-    /// min_y - 1 -> max_y - 1
-    /// ```
-    /// Max value is wrapped to minimum because iteration range `min_x..max_x` doesn't include
-    /// `max_x`.
-    fn wrap(bounds: &IRect, xy: IVec2) -> IVec2 {
-        let mut x = xy.x;
-        let mut y = xy.y;
+        fn decay_states(states: &str) -> Result<u8, RuleError> {
+            match states.parse::<u8>() {
+                Ok(states @ 2..) => Ok(states - 1),
+                _ => Err(RuleError::InvalidStates(states.to_owned())),
+            }
+        }
 
-        let min_x = bounds.min.x;
-        let max_x = bounds.max.x;
+        let parts = rule.trim().split('/').map(str::trim).collect::<Vec<_>>();
 
-        // Wrap horizontally.
-        if x < min_x {
-            x = max_x - (x - min_x).abs();
-        } else if x >= max_x {
-            x = min_x + (x - max_x).abs();
+        if let [survival, birth, states] = parts[..] {
+            if parts
+                .iter()
+                .all(|part| part.chars().all(|c| c.is_ascii_digit()))
+            {
+                return Ok(Self {
+                    birth: births(birth)?,
+                    survival: neighbor_counts(survival)?,
+                    decay_states: decay_states(states)?,
+                });
+            }
         }
 
-        let min_y = bounds.min.y;
-        let max_y = bounds.max.y;
+        let mut birth = None;
+        let mut survival = None;
+        let mut states = None;
 
-        // Wrap vertically.
-        if y < min_y {
-            y = max_y - (y - min_y).abs();
-        } else if y >= max_y {
-            y = min_y + (y - max_y).abs();
+        for part in parts {
+            let mut chars = part.chars();
+            let tag = chars.next().map(|c| c.to_ascii_uppercase());
+            let counts = chars.as_str();
+            match tag {
+                Some('B') if birth.is_none() => birth = Some(births(counts)?),
+                Some('S') if survival.is_none() => survival = Some(neighbor_counts(counts)?),
+                Some('C') if states.is_none() => states = Some(decay_states(counts)?),
+                _ => return Err(RuleError::Malformed(rule.to_owned())),
+            }
         }
 
-        IVec2 { x, y }
+        Ok(Self {
+            birth: birth.ok_or(RuleError::MissingBirth)?,
+            survival: survival.ok_or(RuleError::MissingSurvival)?,
+            decay_states: states.unwrap_or(1),
+        })
     }
+}
 
-    let life = life.into_inner();
-    life.max_age = 1;
+impl Default for Rule {
+    /// Conway's Game of Life, "B3/S23".
+    fn default() -> Self {
+        let mut birth = [false; 9];
+        birth[3] = true;
 
-    for action in actions.read() {
-        if let InputAction::AdvanceSimulation = action {
-            // Re-borrow.
-            debug!("Hash map capacity is {}", life.cells.capacity());
+        let mut survival = [false; 9];
+        survival[2] = true;
+        survival[3] = true;
 
-            let mut next_gen: HashMap<IVec2, Cell> = HashMap::with_capacity(life.cells.capacity());
+        Self {
+            birth,
+            survival,
+            decay_states: 1,
+        }
+    }
+}
 
-            let min_x = life.bounds.min.x;
-            let max_x = life.bounds.max.x;
-            let min_y = life.bounds.min.y;
-            let max_y = life.bounds.max.y;
+impl TryFrom<String> for Rule {
+    type Error = RuleError;
 
-            for y in min_y..max_y {
-                for x in min_x..max_x {
-                    let pt = IVec2::new(x, y);
+    fn try_from(rule: String) -> Result<Self, Self::Error> {
+        Self::parse(&rule)
+    }
+}
 
-                    // We count the number of alive cells, including the inner cell, in the
-                    // neighborhood of each cell.
+impl From<Rule> for String {
+    fn from(rule: Rule) -> Self {
+        rule.to_string()
+    }
+}
 
-                    // Extend `NEIGHBOR_OFFSET` with an invariant offset for the inner cell.
-                    let offsets = NEIGHBOR_OFFSETS
-                        .iter()
-                        .chain([IVec2 { x: 0, y: 0 }].iter())
-                        .collect::<Vec<_>>();
-
-                    let mut count = 0;
-                    for offset in offsets {
-                        let pt = wrap(&life.bounds, pt + *offset);
-                        if let Some(cell) = life.cells.get(&pt) {
-                            if cell.alive {
-                                count += 1;
-                            }
-                        }
-                    }
+impl std::fmt::Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn counts(neighbors: &[bool; 9]) -> String {
+            (0..9)
+                .filter(|n| neighbors[*n])
+                .map(ToString::to_string)
+                .collect()
+        }
 
-                    // If the count is 3, then the  state of the inner cell in the next generation
-                    // is alive; if the count is 4, then the state of the inner cell remains the
-                    // same; if the count is anything else, then the state of the inner cell is
-                    // dead.
-                    match count {
-                        3 | 4 => {
-                            // Cell at `pt` either stays alive or spawns new life.
-                            if let Some(cell) = life.cells.get(&pt) {
-                                let cell = Cell::new(cell.alive, cell.age + 1);
-                                if cell.age > life.max_age {
-                                    life.max_age = cell.age;
-                                }
-                                next_gen.insert(pt, cell);
-                            } else if count == 3 {
-                                next_gen.insert(pt, Cell::default());
-                            }
-                        }
-                        _ => {} // Cell at `pt` dies.
-                    }
-                }
-            }
+        write!(f, "B{}/S{}", counts(&self.birth), counts(&self.survival))?;
+        if self.decay_states > 1 {
+            write!(f, "/C{}", u16::from(self.decay_states) + 1)?;
+        }
+
+        Ok(())
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RuleError {
+    /// The rule isn't of the form "B.../S...".
+    Malformed(String),
+    /// The birth ("B...") condition is missing.
+    MissingBirth,
+    /// The survival ("S...") condition is missing.
+    MissingSurvival,
+    /// A condition contains something other than a neighbor count 0-8.
+    InvalidNeighborCount(char),
+    /// A condition contains a neighbor count more than once.
+    DuplicateNeighborCount(char),
+    /// The birth condition contains 0, i.e. cells are born without live neighbors.
+    BirthWithoutNeighbors,
+    /// The number of states ("C...") isn't a number from 2 to 255.
+    InvalidStates(String),
+}
 
-            if life.history.len() >= Life::MAX_HISTORY_SIZE {
-                life.history.pop_back();
+impl std::fmt::Display for RuleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Malformed(rule) => write!(f, "rule `{rule}` isn't of the form `B.../S...`"),
+            Self::MissingBirth => write!(f, "missing birth (`B...`) condition"),
+            Self::MissingSurvival => write!(f, "missing survival (`S...`) condition"),
+            Self::InvalidNeighborCount(c) => write!(f, "invalid neighbor count `{c}`"),
+            Self::DuplicateNeighborCount(c) => write!(f, "duplicate neighbor count `{c}`"),
+            Self::BirthWithoutNeighbors => {
+                write!(f, "birth without neighbors (`B0`) is unsupported")
             }
-            life.history
-                .push_front(std::mem::replace(&mut life.cells, next_gen));
-            life.generation += 1;
+            Self::InvalidStates(states) => write!(f, "invalid number of states `{states}`"),
         }
     }
 }
 
+impl std::error::Error for RuleError {}
 
-/// Rewind the simulation a single tick (generation).
-pub fn rewind_simulation(
-    mut life: ResMut<'_, Life>,
-    mut actions: EventReader<'_, '_, InputAction>,
-) {
-    for action in actions.read() {
-        if let InputAction::RewindSimulation = action {
-            let Some(prev_gen) = life.history.pop_front() else {
-                info!("History is empty");
-                return;
-            };
-            life.cells = prev_gen;
-            life.generation -= 1;
-        }
+
+#[derive(Resource, Deref, DerefMut)]
+pub struct SimulationUpdateTimer(pub Timer);
+
+impl SimulationUpdateTimer {
+    /// Create a timer that finishes `tps` times per second.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn new(tps: i32) -> Self {
+        Self(Timer::from_seconds(1.0 / tps as f32, TimerMode::Repeating))
+    }
+
+    /// Change the timer to finish `tps` times per second, carrying over the elapsed fraction of
+    /// the current tick so changing the rate doesn't stutter or tick twice.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn retune(&mut self, tps: i32) {
+        let fraction = self.0.fraction();
+        let duration = Duration::from_secs_f32(1.0 / tps as f32);
+
+        self.0.set_duration(duration);
+        self.0.set_elapsed(duration.mul_f32(fraction));
     }
 }
 
 
-fn toggle_cell(mut life: ResMut<'_, Life>, mut actions: EventReader<'_, '_, InputAction>) {
-    for action in actions.read() {
-        if let InputAction::ToggleCell(xy) = action {
-            if life.cells.contains_key(xy) {
-                life.cells.remove(xy);
-            } else {
-                life.cells.insert(*xy, Cell::default());
-            }
+/// Seed of random soups. The same seed, density, and world bounds always generate the same soup,
+/// across runs too.
+#[derive(Resource, Deref, DerefMut)]
+pub struct SoupSeed(pub u64);
+
+impl SoupSeed {
+    /// Move on to the next seed.
+    pub fn reroll(&mut self) {
+        self.0 = self.0.wrapping_add(1);
+    }
+}
+
+impl Default for SoupSeed {
+    fn default() -> Self {
+        Self(config::sim::DEFAULT_SOUP_SEED)
+    }
+}
+
+
+/// A cell in `Life::cells`. Dead cells aren't stored, so every stored cell is either alive, or
+/// dying under a "Generations" rule, and counts as dead for the population and extinction.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cell {
+    /// `false` for dying cells, which are drawn fading out, see `state`.
+    pub alive: bool,
+    pub age: u32,
+    /// Remaining decay of a dying cell of a "Generations" rule, see `Rule::decay_states`. Always
+    /// 0 for live cells.
+    #[serde(default)]
+    pub state: u8,
+    /// Color of a live cell of the "Immigration" rule variant.
+    #[serde(default)]
+    pub color: ImmigrationColor,
+}
+
+impl Cell {
+    fn new(alive: bool, age: u32) -> Self {
+        Self {
+            alive,
+            age,
+            state: 0,
+            color: ImmigrationColor::default(),
+        }
+    }
+
+    /// Return a newborn cell of the `color`.
+    pub fn with_color(color: ImmigrationColor) -> Self {
+        Self {
+            color,
+            ..Self::default()
+        }
+    }
+
+    /// Return a dying cell in the decay `state`.
+    fn dying(state: u8) -> Self {
+        Self {
+            alive: false,
+            age: 0,
+            state,
+            color: ImmigrationColor::default(),
         }
     }
 }
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self::new(true, 0)
+    }
+}
+
+
+#[derive(Resource)]
+pub struct Life {
+    pub bounds: IRect,
+    pub history: VecDeque<HashMap<IVec2, Cell>>,
+    /// Generations undone by rewinding, most recent first.
+    pub redo: VecDeque<HashMap<IVec2, Cell>>,
+    pub cells: HashMap<IVec2, Cell>,
+    pub generation: u32,
+    /// Age of the oldest live cell.
+    pub max_age: u32,
+    /// Maximum number of past generations kept in `history`.
+    history_limit: usize,
+}
+
+impl Life {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            bounds: Self::centered_bounds(width, height),
+            cells: HashMap::new(),
+            history: VecDeque::with_capacity(config::sim::DEFAULT_HISTORY_LIMIT),
+            redo: VecDeque::new(),
+            generation: 0,
+            max_age: 0,
+            history_limit: config::sim::DEFAULT_HISTORY_LIMIT,
+        }
+    }
+
+    /// Return bounds of about `width` by `height` cells, centered on the origin.
+    #[allow(clippy::cast_possible_wrap)]
+    fn centered_bounds(width: u32, height: u32) -> IRect {
+        let half_width = (width / 2) as i32;
+        let half_height = (height / 2) as i32;
+
+        let max = IVec2 {
+            x: half_width,
+            y: half_height,
+        };
+        let min = -max;
+
+        IRect::from_corners(min, max)
+    }
+
+    /// Resize the world, keeping it centered on the origin. Live cells that fall outside of the
+    /// new bounds are dropped, also from the history, so rewinding doesn't bring them back.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.set_bounds(Self::centered_bounds(width, height));
+    }
+
+    /// Move the corners of the world to `bounds`. Live cells that fall outside of the new bounds
+    /// are dropped, also from the history, so rewinding doesn't bring them back.
+    pub fn set_bounds(&mut self, bounds: IRect) {
+        self.bounds = bounds;
+
+        let in_bounds = |xy: &IVec2, _: &mut Cell| {
+            (bounds.min.x..bounds.max.x).contains(&xy.x)
+                && (bounds.min.y..bounds.max.y).contains(&xy.y)
+        };
+        self.cells.retain(in_bounds);
+        for gen in self.history.iter_mut().chain(self.redo.iter_mut()) {
+            gen.retain(in_bounds);
+        }
+
+        self.update_max_age();
+    }
+
+    pub fn history_limit(&self) -> usize {
+        self.history_limit
+    }
+
+    /// Set the maximum number of past generations kept in `history`. If the limit shrinks, the
+    /// oldest generations are dropped immediately.
+    pub fn set_history_limit(&mut self, limit: usize) {
+        self.history_limit = limit;
+        self.history.truncate(limit);
+    }
+
+    /// Push the previous generation onto the history, dropping the oldest generation if the
+    /// history is full.
+    fn push_history(&mut self, prev_gen: HashMap<IVec2, Cell>) {
+        if self.history_limit == 0 {
+            return;
+        }
+        if self.history.len() >= self.history_limit {
+            self.history.pop_back();
+        }
+        self.history.push_front(prev_gen);
+    }
+
+    /// Compute the next generation without advancing the simulation. Has no dependencies on the
+    /// ECS, so it can be used headless, e.g. in tests and benchmarks.
+    pub fn step(&self, config: &SimulationConfig) -> HashMap<IVec2, Cell> {
+        let mut next_gen: HashMap<IVec2, Cell> = HashMap::with_capacity(self.cells.capacity());
+
+        let offsets = config.neighborhood.offsets();
+
+        // Only cells in the neighborhood of a live cell can be alive in the next generation. Dying
+        // cells are candidates themselves, so they keep decaying.
+        let mut candidates: HashSet<IVec2> =
+            HashSet::with_capacity(self.cells.len() * (offsets.len() + 1));
+        for (pt, cell) in &self.cells {
+            candidates.insert(*pt);
+            if cell.alive {
+                candidates.extend(
+                    offsets
+                        .iter()
+                        .filter_map(|offset| self.neighbor(config.topology, *pt, *offset)),
+                );
+            }
+        }
+
+        for pt in candidates {
+            let (count, count_b) = self.count_live_neighbors(config, pt);
+
+            match self.cells.get(&pt) {
+                Some(cell) if cell.alive => {
+                    // Cell at `pt` either survives or dies.
+                    if config.rule.survival[count] {
+                        let cell = Cell {
+                            age: cell.age + 1,
+                            ..*cell
+                        };
+                        next_gen.insert(pt, cell);
+                    } else if config.rule.decay_states > 1 {
+                        next_gen.insert(pt, Cell::dying(config.rule.decay_states - 1));
+                    }
+                }
+                Some(cell) => {
+                    // Cell at `pt` keeps decaying until it vanishes.
+                    if cell.state > 1 {
+                        next_gen.insert(pt, Cell::dying(cell.state - 1));
+                    }
+                }
+                None => {
+                    if config.rule.birth[count] {
+                        // Cell at `pt` spawns new life, taking the majority color of its parents.
+                        // Ties go to the first color.
+                        let color = match config.variant {
+                            RuleVariant::Immigration if count_b * 2 > count => ImmigrationColor::B,
+                            _ => ImmigrationColor::A,
+                        };
+                        next_gen.insert(pt, Cell::with_color(color));
+                    }
+                }
+            }
+        }
+
+        next_gen
+    }
+
+    /// Map the neighbor of `xy` at `offset` into the world, or `None` if it lies outside of a
+    /// walled world.
+    fn neighbor(&self, topology: Topology, xy: IVec2, offset: IVec2) -> Option<IVec2> {
+        match topology {
+            Topology::Torus => Some(wrap(&self.bounds, xy + offset)),
+            Topology::Wall => Some(xy + offset).filter(|xy| self.in_bounds(*xy)),
+            Topology::Unbounded => Some(xy + offset),
+        }
+    }
+
+    /// Return the number of live neighbors of the cell at `xy`, and how many of them are of the
+    /// second color of the "Immigration" rule variant.
+    fn count_live_neighbors(&self, config: &SimulationConfig, xy: IVec2) -> (usize, usize) {
+        let mut count = 0;
+        let mut count_b = 0;
+        for offset in config.neighborhood.offsets() {
+            if let Some(cell) = self
+                .neighbor(config.topology, xy, *offset)
+                .and_then(|xy| self.cells.get(&xy))
+            {
+                if cell.alive {
+                    count += 1;
+                    if cell.color == ImmigrationColor::B {
+                        count_b += 1;
+                    }
+                }
+            }
+        }
+
+        (count, count_b)
+    }
+
+    /// Return the number of live neighbors of the cell at `xy`, as counted when advancing the
+    /// simulation.
+    pub fn live_neighbors(&self, config: &SimulationConfig, xy: IVec2) -> usize {
+        self.count_live_neighbors(config, xy).0
+    }
+
+    /// Advance the simulation a single tick (generation). Returns the cells born and died.
+    pub fn advance(&mut self, config: &SimulationConfig) -> GenerationDelta {
+        debug!("Hash map capacity is {}", self.cells.capacity());
+
+        let next_gen = self.step(config);
+
+        let alive =
+            |cells: &HashMap<IVec2, Cell>, xy: &IVec2| cells.get(xy).is_some_and(|cell| cell.alive);
+        let alive_not_in = |a: &HashMap<IVec2, Cell>, b: &HashMap<IVec2, Cell>| {
+            a.iter()
+                .filter(|(xy, cell)| cell.alive && !alive(b, xy))
+                .map(|(xy, _)| *xy)
+                .collect()
+        };
+        let born = alive_not_in(&next_gen, &self.cells);
+        let died = alive_not_in(&self.cells, &next_gen);
+
+        let prev_gen = std::mem::replace(&mut self.cells, next_gen);
+        self.push_history(prev_gen);
+        self.redo.clear();
+        self.update_max_age();
+        self.generation += 1;
+
+        if config.topology == Topology::Unbounded {
+            self.fit_bounds();
+        }
+
+        GenerationDelta {
+            born,
+            died,
+            generation: self.generation,
+        }
+    }
+
+    /// Fit the world bounds to the live cells, leaving `config::sim::UNBOUNDED_MARGIN` dead cells
+    /// on each side. So the bounds don't change every tick, they are only refitted once a live cell
+    /// comes within half the margin of an edge, or the bounds are more than twice as wide or high
+    /// as needed. Returns `true` if the bounds changed.
+    pub fn fit_bounds(&mut self) -> bool {
+        let margin = config::sim::UNBOUNDED_MARGIN;
+
+        let Some(live) = self.live_bounds() else {
+            return false;
+        };
+        // `live` is inclusive, while `bounds.max` is exclusive.
+        let fitted = IRect {
+            min: live.min - margin,
+            max: live.max + 1 + margin,
+        };
+
+        let near_edge = live.min.cmplt(self.bounds.min + margin / 2).any()
+            || live.max.cmpge(self.bounds.max - margin / 2).any();
+        let oversized = self.bounds.size().cmpgt(fitted.size() * 2).any();
+        if !near_edge && !oversized {
+            return false;
+        }
+
+        debug!("Fitted bounds {:?} to {:?}", self.bounds, fitted);
+        self.bounds = fitted;
+
+        true
+    }
+
+    /// Recompute the age of the oldest live cell. Must be called after replacing `cells`.
+    pub fn update_max_age(&mut self) {
+        self.max_age = self.cells.values().map(|cell| cell.age).max().unwrap_or(0);
+    }
+
+    /// Return `true` if `xy` lies within the world bounds.
+    pub fn in_bounds(&self, xy: IVec2) -> bool {
+        (self.bounds.min.x..self.bounds.max.x).contains(&xy.x)
+            && (self.bounds.min.y..self.bounds.max.y).contains(&xy.y)
+    }
+
+    /// Return the empty region around `start`, spreading orthogonally across empty cells and
+    /// stopping at occupied cells and the world bounds. Returns `None` if `start` isn't an empty
+    /// cell within the world bounds, or if the region has more than `limit` cells.
+    pub fn flood_fill(&self, start: IVec2, limit: usize) -> Option<Vec<IVec2>> {
+        const OFFSETS: [IVec2; 4] = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y];
+
+        let empty = |xy: IVec2| self.in_bounds(xy) && !self.cells.contains_key(&xy);
+        if !empty(start) {
+            return None;
+        }
+
+        let mut region = vec![start];
+        let mut visited = HashSet::from_iter([start]);
+        let mut queue = VecDeque::from([start]);
+        while let Some(xy) = queue.pop_front() {
+            for offset in OFFSETS {
+                let neighbor = xy + offset;
+                if empty(neighbor) && visited.insert(neighbor) {
+                    if region.len() == limit {
+                        return None;
+                    }
+                    region.push(neighbor);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+
+        Some(region)
+    }
+
+    /// Insert the cells of a pattern into the world. Cells that fall outside of the world bounds
+    /// are clipped.
+    pub fn insert_pattern(&mut self, cells: impl IntoIterator<Item = (IVec2, Cell)>) {
+        let mut clipped = 0;
+        for (xy, cell) in cells {
+            if self.in_bounds(xy) {
+                self.cells.insert(xy, cell);
+            } else {
+                clipped += 1;
+            }
+        }
+
+        if clipped > 0 {
+            warn!("Clipped {clipped} cells outside of the world bounds");
+        }
+    }
+
+    /// Return the number of live cells. Dying cells of "Generations" rules aren't counted.
+    pub fn population(&self) -> usize {
+        self.cells.values().filter(|cell| cell.alive).count()
+    }
+
+    /// Return `true` if the cell at `xy` is alive. Dying cells of "Generations" rules aren't.
+    pub fn is_alive(&self, xy: IVec2) -> bool {
+        self.cells.get(&xy).is_some_and(|cell| cell.alive)
+    }
+
+    /// Return the age of the cell at `xy`, or `None` if it isn't alive.
+    pub fn age_of(&self, xy: IVec2) -> Option<u32> {
+        self.cells
+            .get(&xy)
+            .filter(|cell| cell.alive)
+            .map(|cell| cell.age)
+    }
+
+    /// Return the bounding box of the live cells, or `None` if there are none. Both corners are
+    /// inclusive. Dying cells of "Generations" rules aren't included.
+    pub fn live_bounds(&self) -> Option<IRect> {
+        self.cells.iter().filter(|(_, cell)| cell.alive).fold(
+            None,
+            |extent, (xy, _)| match extent {
+                None => Some(IRect::from_corners(*xy, *xy)),
+                Some(extent) => Some(IRect {
+                    min: xy.min(extent.min),
+                    max: xy.max(extent.max),
+                }),
+            },
+        )
+    }
+
+    /// Serialize the live cells into the RLE format. The header describes the bounding box of the
+    /// live cells rather than the world bounds, and the `rule` the cells evolve by.
+    pub fn to_rle(&self, rule: &Rule) -> String {
+        /// Maximum line length of the pattern body.
+        const LINE_WIDTH: usize = 70;
+
+        fn push_run(tokens: &mut Vec<String>, count: i32, tag: char) {
+            if count == 1 {
+                tokens.push(tag.to_string());
+            } else {
+                tokens.push(format!("{count}{tag}"));
+            }
+        }
+
+        let Some(IRect { min, max }) = self.live_bounds() else {
+            return format!("x = 0, y = 0, rule = {rule}\n!");
+        };
+
+        let mut tokens = Vec::new();
+        let mut separators = 0;
+
+        // RLE rows run top to bottom.
+        for y in (min.y..=max.y).rev() {
+            let mut row = Vec::new();
+            let mut x = min.x;
+            while x <= max.x {
+                let run_alive = self.is_alive(IVec2::new(x, y));
+                let start = x;
+                while x <= max.x && self.is_alive(IVec2::new(x, y)) == run_alive {
+                    x += 1;
+                }
+
+                // Trailing dead cells are implied.
+                if run_alive || x <= max.x {
+                    push_run(&mut row, x - start, if run_alive { 'o' } else { 'b' });
+                }
+            }
+
+            if row.is_empty() {
+                separators += 1;
+            } else {
+                if separators > 0 {
+                    push_run(&mut tokens, separators, '$');
+                }
+                tokens.append(&mut row);
+                separators = 1;
+            }
+        }
+        tokens.push(String::from("!"));
+
+        let mut rle = format!(
+            "x = {}, y = {}, rule = {rule}\n",
+            max.x - min.x + 1,
+            max.y - min.y + 1
+        );
+        let mut line_len = 0;
+        for token in tokens {
+            if line_len + token.len() > LINE_WIDTH {
+                rle.push('\n');
+                line_len = 0;
+            }
+            line_len += token.len();
+            rle.push_str(&token);
+        }
+
+        rle
+    }
+
+    /// Save the world and the rule it evolves by to `path` in the RON format.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the state can't be serialized or written to `path`.
+    pub fn save_to_path(&self, config: &SimulationConfig, path: &Path) -> io::Result<()> {
+        let state = SavedState {
+            bounds: self.bounds,
+            generation: self.generation,
+            rule: config.rule,
+            variant: config.variant,
+            neighborhood: config.neighborhood,
+            topology: config.topology,
+            cells: self.cells.iter().map(|(xy, cell)| (*xy, *cell)).collect(),
+        };
+
+        let ron = ron::ser::to_string_pretty(&state, ron::ser::PrettyConfig::default())
+            .map_err(io::Error::other)?;
+        std::fs::write(path, ron)
+    }
+
+    /// Load a world saved by [`Life::save_to_path`] from `path`. On success, `config` is updated
+    /// with the rule the world evolves by.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `path` can't be read, isn't a valid saved state, or contains cells
+    /// outside of the saved world bounds.
+    pub fn load_from_path(path: &Path, config: &mut SimulationConfig) -> Result<Self, LoadError> {
+        let state: SavedState = ron::from_str(&std::fs::read_to_string(path)?)?;
+
+        if state.bounds.is_empty() {
+            return Err(LoadError::InvalidBounds(state.bounds));
+        }
+
+        let mut life = Self {
+            bounds: state.bounds,
+            cells: HashMap::with_capacity(state.cells.len()),
+            history: VecDeque::with_capacity(config::sim::DEFAULT_HISTORY_LIMIT),
+            redo: VecDeque::new(),
+            generation: state.generation,
+            max_age: 0,
+            history_limit: config::sim::DEFAULT_HISTORY_LIMIT,
+        };
+        for (xy, cell) in state.cells {
+            if !life.in_bounds(xy) {
+                return Err(LoadError::OutOfBounds(xy));
+            }
+            life.cells.insert(xy, cell);
+        }
+        life.update_max_age();
+
+        config.rule = state.rule;
+        config.variant = state.variant;
+        config.neighborhood = state.neighborhood;
+        config.topology = state.topology;
+
+        Ok(life)
+    }
+}
+
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    Parse(ron::error::SpannedError),
+    /// The world bounds are empty.
+    InvalidBounds(IRect),
+    /// A cell lies outside of the world bounds.
+    OutOfBounds(IVec2),
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => err.fmt(f),
+            Self::Parse(err) => write!(f, "invalid saved state: {err}"),
+            Self::InvalidBounds(bounds) => write!(f, "invalid world bounds {bounds:?}"),
+            Self::OutOfBounds(xy) => write!(f, "cell {xy} lies outside of the world bounds"),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Parse(err) => Some(err),
+            Self::InvalidBounds(_) | Self::OutOfBounds(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for LoadError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        Self::Parse(err)
+    }
+}
+
+
+/// Saved state of the simulation, see [`Life::save_to_path`].
+#[derive(Serialize, Deserialize)]
+struct SavedState {
+    bounds: IRect,
+    generation: u32,
+    rule: Rule,
+    #[serde(default)]
+    variant: RuleVariant,
+    neighborhood: Neighborhood,
+    topology: Topology,
+    cells: Vec<(IVec2, Cell)>,
+}
+
+
+fn setup_simulation(
+    mut life: ResMut<'_, Life>,
+    initial_pattern: Res<'_, InitialPattern>,
+    seed: Res<'_, SoupSeed>,
+    config: Res<'_, SimulationConfig>,
+) {
+    let cells = initial_pattern.cells(life.bounds, **seed, config.variant);
+    life.insert_pattern(cells);
+}
+
+
+/// Load the startup pattern from `config::patterns::STARTUP_PATTERN_PATH`, if present.
+fn load_startup_pattern() -> Option<Vec<(IVec2, Cell)>> {
+    let path = Path::new(config::patterns::STARTUP_PATTERN_PATH);
+    if cfg!(target_arch = "wasm32") || !path.exists() {
+        return None;
+    }
+
+    load_pattern_file(path)
+}
+
+/// Load a pattern in the RLE format from `path`, logging why if it can't be loaded.
+#[cfg(not(target_arch = "wasm32"))]
+fn load_pattern_file(path: &Path) -> Option<Vec<(IVec2, Cell)>> {
+    let input = match std::fs::read_to_string(path) {
+        Ok(input) => input,
+        Err(err) => {
+            warn!("Failed to read pattern '{}': {err}", path.display());
+            return None;
+        }
+    };
+
+    match patterns::parse_rle(&input) {
+        Ok(cells) => {
+            info!("Loaded pattern '{}'", path.display());
+            Some(cells)
+        }
+        Err(err) => {
+            warn!("Failed to parse pattern '{}': {err}", path.display());
+            None
+        }
+    }
+}
+
+/// There is no filesystem to load patterns from on the web.
+#[cfg(target_arch = "wasm32")]
+fn load_pattern_file(path: &Path) -> Option<Vec<(IVec2, Cell)>> {
+    warn!(
+        "Can't load pattern '{}', loading files isn't supported on the web",
+        path.display()
+    );
+    None
+}
+
+/// Advance the simulation on every tick, or rewind it when playing in reverse. Once there is no
+/// more history to rewind, the simulation is paused. Tick rates above the frame rate tick several
+/// times per frame, up to `config::sim::MAX_STEPS_PER_FRAME`.
+fn tick_simulation_update_timer(
+    mut timer: ResMut<'_, SimulationUpdateTimer>,
+    time: Res<'_, Time>,
+    config: Res<'_, SimulationConfig>,
+    life: Res<'_, Life>,
+    mut next_state: ResMut<'_, NextState<AppState>>,
+    mut actions: EventWriter<'_, InputAction>,
+) {
+    let ticks = if config.unlimited {
+        1
+    } else {
+        timer
+            .tick(time.delta())
+            .times_finished_this_tick()
+            .min(config::sim::MAX_STEPS_PER_FRAME)
+    };
+    if ticks == 0 {
+        return;
+    }
+
+    // @REVIEW: **Technically** not an *input* action.
+    if !config.reverse {
+        for _ in 0..ticks {
+            actions.send(InputAction::AdvanceSimulation);
+        }
+    } else if life.history.is_empty() {
+        info!("Reached the start of the history");
+        next_state.set(AppState::Paused);
+    } else {
+        for _ in 0..ticks {
+            actions.send(InputAction::RewindSimulation);
+        }
+    }
+}
+
+
+/// Advance the simulation until it reaches the target generation, then pause it. At the tick rate,
+/// pausing the simulation cancels the run.
+fn run_until_target(
+    mut target: ResMut<'_, RunTarget>,
+    life: Res<'_, Life>,
+    state: Res<'_, State<AppState>>,
+    mut next_state: ResMut<'_, NextState<AppState>>,
+    mut actions: EventWriter<'_, InputAction>,
+) {
+    let Some(generation) = target.generation else {
+        return;
+    };
+    let started = target.is_changed();
+
+    if life.generation >= generation {
+        info!("Reached target generation {generation}");
+        target.generation = None;
+        next_state.set(AppState::Paused);
+        return;
+    }
+
+    if target.fast {
+        // Don't let the timer advance the simulation too.
+        if *state.get() == AppState::Running {
+            next_state.set(AppState::Paused);
+        }
+
+        let steps = (generation - life.generation).min(config::sim::MAX_STEPS_PER_FRAME);
+        actions.send(InputAction::StepMany(steps));
+    } else if started {
+        next_state.set(AppState::Running);
+    } else if *state.get() == AppState::Paused {
+        info!("Cancelled running until generation {generation}");
+        target.generation = None;
+    }
+}
+
+
+/// Reset simulation update timer.
+///
+/// Executed on entering the `AppState::Paused` state.
+pub fn reset_simulation_update_timer(mut timer: ResMut<'_, SimulationUpdateTimer>) {
+    timer.reset();
+}
+
+
+/// Advance the simulation a single tick (generation), or many ticks at once.
+pub fn advance_simulation(
+    mut life: ResMut<'_, Life>,
+    config: Res<'_, SimulationConfig>,
+    mut stats: ResMut<'_, GenerationStats>,
+    mut step_times: ResMut<'_, StepTimes>,
+    mut population_history: ResMut<'_, PopulationHistory>,
+    mut actions: EventReader<'_, '_, InputAction>,
+    mut ev_grid_changed: EventWriter<'_, GridChanged>,
+    mut ev_extinct: EventWriter<'_, BoardExtinct>,
+    mut ev_delta: EventWriter<'_, GenerationDelta>,
+) {
+    for action in actions.read() {
+        let steps = match action {
+            InputAction::AdvanceSimulation => 1,
+            InputAction::StepMany(steps) => *steps,
+            _ => continue,
+        };
+
+        let populated = life.population() > 0;
+        let bounds = life.bounds;
+        for _ in 0..steps {
+            let start = Instant::now();
+            let delta = life.advance(&config);
+            step_times.push(start.elapsed());
+            *stats = delta.stats();
+
+            population_history.push(GenerationRecord {
+                generation: life.generation,
+                population: life.population(),
+                births: stats.births,
+                deaths: stats.deaths,
+            });
+            ev_delta.send(delta);
+        }
+        if life.bounds != bounds {
+            ev_grid_changed.send(GridChanged);
+        }
+        if populated && life.population() == 0 {
+            ev_extinct.send(BoardExtinct {
+                generation: life.generation,
+            });
+        }
+    }
+}
+
+
+/// Log the board going extinct, and optionally pause the running simulation.
+fn pause_on_extinction(
+    config: Res<'_, SimulationConfig>,
+    state: Res<'_, State<AppState>>,
+    mut next_state: ResMut<'_, NextState<AppState>>,
+    mut ev_extinct: EventReader<'_, '_, BoardExtinct>,
+) {
+    for event in ev_extinct.read() {
+        info!("Board went extinct at generation {}", event.generation);
+        if config.detect_extinction && *state.get() == AppState::Running {
+            next_state.set(AppState::Paused);
+        }
+    }
+}
+
+
+/// Wrap `xy` into `bounds`, joining opposite edges, however far outside of `bounds` it lies. The
+/// maximum corner is exclusive, i.e. `max` wraps to `min` and `min - 1` wraps to `max - 1`.
+/// `bounds` must not be empty.
+fn wrap(bounds: &IRect, xy: IVec2) -> IVec2 {
+    let size = bounds.size();
+
+    IVec2 {
+        x: bounds.min.x + (xy.x - bounds.min.x).rem_euclid(size.x),
+        y: bounds.min.y + (xy.y - bounds.min.y).rem_euclid(size.y),
+    }
+}
+
+
+/// Detect the period of the current generation by finding the most recent generation in the
+/// history with the same live cells. Optionally, pause the running simulation once it reaches a
+/// still life or an oscillator.
+fn detect_period(
+    life: Res<'_, Life>,
+    config: Res<'_, SimulationConfig>,
+    state: Res<'_, State<AppState>>,
+    mut period: ResMut<'_, DetectedPeriod>,
+    mut next_state: ResMut<'_, NextState<AppState>>,
+    mut actions: EventReader<'_, '_, InputAction>,
+) {
+    let mut advanced = false;
+    for action in actions.read() {
+        if let InputAction::AdvanceSimulation = action {
+            advanced = true;
+        }
+    }
+
+    // Comparing against the whole history is costly, so only do so when the board may have
+    // changed. Advancing always changes it.
+    if !life.is_changed() {
+        return;
+    }
+
+    period.0 = life
+        .history
+        .iter()
+        .position(|prev_gen| same_live_cells(prev_gen, &life.cells))
+        .map(|i| i + 1);
+
+    if !advanced || *state.get() != AppState::Running {
+        return;
+    }
+
+    match period.0 {
+        Some(1) if config.detect_stable => {
+            info!("Reached a still life at generation {}", life.generation);
+            next_state.set(AppState::Paused);
+        }
+        Some(period) if period > 1 && config.detect_oscillation => {
+            info!(
+                "Reached a period {period} oscillator at generation {}",
+                life.generation
+            );
+            next_state.set(AppState::Paused);
+        }
+        _ => {}
+    }
+}
+
+
+/// Return a random soup of cells within `bounds`, each alive with a probability of `density`.
+pub fn random_soup(
+    bounds: IRect,
+    seed: u64,
+    density: f32,
+    variant: RuleVariant,
+) -> HashMap<IVec2, Cell> {
+    let density = f64::from(density.clamp(0.0, 1.0));
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let mut cells = HashMap::new();
+    for y in bounds.min.y..bounds.max.y {
+        for x in bounds.min.x..bounds.max.x {
+            if rng.gen_bool(density) {
+                // Both colors are equally likely in an "Immigration" soup.
+                let color = match variant {
+                    RuleVariant::Immigration if rng.gen_bool(0.5) => ImmigrationColor::B,
+                    _ => ImmigrationColor::A,
+                };
+                cells.insert(IVec2::new(x, y), Cell::with_color(color));
+            }
+        }
+    }
+
+    cells
+}
+
+
+/// Replace the live cells with a random soup.
+fn randomize_board(
+    mut life: ResMut<'_, Life>,
+    mut seed: ResMut<'_, SoupSeed>,
+    config: Res<'_, SimulationConfig>,
+    mut actions: EventReader<'_, '_, InputAction>,
+) {
+    for action in actions.read() {
+        if let InputAction::RandomizeBoard { density, reroll } = action {
+            if *reroll {
+                seed.reroll();
+            }
+
+            life.cells = random_soup(life.bounds, **seed, *density, config.variant);
+            life.redo.clear();
+            life.update_max_age();
+            debug!(
+                "Randomized board with seed {} and density {density}",
+                **seed
+            );
+        }
+    }
+}
+
+
+/// Clear the board, resetting the generation and the history.
+fn clear_board(mut life: ResMut<'_, Life>, mut actions: EventReader<'_, '_, InputAction>) {
+    for action in actions.read() {
+        if let InputAction::ClearBoard = action {
+            life.cells.clear();
+            life.history.clear();
+            life.redo.clear();
+            life.generation = 0;
+            life.max_age = 0;
+        }
+    }
+}
+
+
+/// Replace the board with the initial pattern, resetting the generation and the history.
+fn reset_simulation(
+    mut life: ResMut<'_, Life>,
+    initial_pattern: Res<'_, InitialPattern>,
+    seed: Res<'_, SoupSeed>,
+    config: Res<'_, SimulationConfig>,
+    mut actions: EventReader<'_, '_, InputAction>,
+) {
+    for action in actions.read() {
+        if let InputAction::ResetSimulation = action {
+            life.cells.clear();
+            life.history.clear();
+            life.redo.clear();
+            life.generation = 0;
+
+            let cells = initial_pattern.cells(life.bounds, **seed, config.variant);
+            life.insert_pattern(cells);
+            life.update_max_age();
+            debug!("Reset to {}", initial_pattern.label());
+        }
+    }
+}
+
+
+/// Reset the generation to 0 and forget the history, keeping the live cells.
+fn reset_generation(mut life: ResMut<'_, Life>, mut actions: EventReader<'_, '_, InputAction>) {
+    for action in actions.read() {
+        if let InputAction::ResetGeneration = action {
+            life.history.clear();
+            life.redo.clear();
+            life.generation = 0;
+        }
+    }
+}
+
+
+/// Take a snapshot of the board, or replace the board with the next or previous snapshot.
+fn snapshot_board(
+    mut life: ResMut<'_, Life>,
+    mut snapshots: ResMut<'_, Snapshots>,
+    mut actions: EventReader<'_, '_, InputAction>,
+) {
+    for action in actions.read() {
+        match action {
+            InputAction::TakeSnapshot => {
+                snapshots.push(life.cells.clone());
+                info!("Took snapshot {}", snapshots.len());
+            }
+            InputAction::CycleSnapshot { next } => {
+                let reset_generation = snapshots.reset_generation;
+                let Some(cells) = snapshots.cycle(*next) else {
+                    info!("No snapshots");
+                    continue;
+                };
+
+                life.cells = cells.clone();
+                life.redo.clear();
+                life.update_max_age();
+                if reset_generation {
+                    life.history.clear();
+                    life.generation = 0;
+                }
+                debug!("Loaded snapshot {:?}", snapshots.current().map(|i| i + 1));
+            }
+            _ => {}
+        }
+    }
+}
+
+
+/// Save the simulation state to `config::save::STATE_PATH`.
+fn save_state(
+    life: Res<'_, Life>,
+    config: Res<'_, SimulationConfig>,
+    mut actions: EventReader<'_, '_, InputAction>,
+) {
+    for action in actions.read() {
+        if let InputAction::SaveState = action {
+            if cfg!(target_arch = "wasm32") {
+                warn!("Saving isn't supported on the web");
+                continue;
+            }
+
+            let path = Path::new(config::save::STATE_PATH);
+            match life.save_to_path(&config, path) {
+                Ok(()) => info!("Saved state to '{}'", path.display()),
+                Err(err) => error!("Failed to save state to '{}': {err}", path.display()),
+            }
+        }
+    }
+}
+
+
+/// Export the statistics of the recorded generations to `config::save::STATS_PATH` as CSV, or
+/// download them on the web.
+fn export_stats(
+    population_history: Res<'_, PopulationHistory>,
+    mut actions: EventReader<'_, '_, InputAction>,
+) {
+    for action in actions.read() {
+        if let InputAction::ExportStats = action {
+            let path = Path::new(config::save::STATS_PATH);
+            match population_history.save_csv(path) {
+                Ok(()) => info!(
+                    "Exported {} generations to '{}'",
+                    population_history.len(),
+                    path.display()
+                ),
+                Err(err) => error!("Failed to export stats to '{}': {err}", path.display()),
+            }
+        }
+    }
+}
+
+
+/// Load the simulation state from `config::save::STATE_PATH`.
+fn load_state(
+    mut life: ResMut<'_, Life>,
+    mut config: ResMut<'_, SimulationConfig>,
+    mut actions: EventReader<'_, '_, InputAction>,
+    mut errors: EventWriter<'_, ShowError>,
+    mut grid_changed: EventWriter<'_, GridChanged>,
+) {
+    for action in actions.read() {
+        if let InputAction::LoadState = action {
+            if cfg!(target_arch = "wasm32") {
+                warn!("Loading isn't supported on the web");
+                continue;
+            }
+
+            let path = Path::new(config::save::STATE_PATH);
+            match Life::load_from_path(path, &mut config) {
+                Ok(mut loaded) => {
+                    info!("Loaded state from '{}'", path.display());
+                    loaded.set_history_limit(life.history_limit());
+                    *life = loaded;
+                    grid_changed.send(GridChanged);
+                }
+                Err(err) => {
+                    error!("Failed to load state from '{}': {err}", path.display());
+                    errors.send(ShowError(format!(
+                        "Failed to load state from '{}': {err}",
+                        path.display()
+                    )));
+                }
+            }
+        }
+    }
+}
+
+
+fn resize_board(
+    mut life: ResMut<'_, Life>,
+    mut actions: EventReader<'_, '_, InputAction>,
+    mut grid_changed: EventWriter<'_, GridChanged>,
+) {
+    for action in actions.read() {
+        match action {
+            InputAction::ResizeBoard { width, height } => life.resize(*width, *height),
+            InputAction::SetBounds(bounds) => life.set_bounds(*bounds),
+            _ => continue,
+        }
+        grid_changed.send(GridChanged);
+        debug!("Resized board to {:?}", life.bounds);
+    }
+}
+
+
+/// Return `true` if both generations have the same live and dying cells, regardless of their age.
+fn same_live_cells(a: &HashMap<IVec2, Cell>, b: &HashMap<IVec2, Cell>) -> bool {
+    a.len() == b.len()
+        && a.iter().all(|(xy, cell)| {
+            b.get(xy).is_some_and(|other| {
+                other.alive == cell.alive && other.state == cell.state && other.color == cell.color
+            })
+        })
+}
+
+
+/// Rewind the simulation a single tick (generation).
+pub fn rewind_simulation(
+    mut life: ResMut<'_, Life>,
+    mut actions: EventReader<'_, '_, InputAction>,
+) {
+    for action in actions.read() {
+        if let InputAction::RewindSimulation = action {
+            let Some(prev_gen) = life.history.pop_front() else {
+                info!("History is empty");
+                return;
+            };
+            let next_gen = std::mem::replace(&mut life.cells, prev_gen);
+            life.redo.push_front(next_gen);
+            life.update_max_age();
+            if life.generation == 0 {
+                warn!("Rewound past generation 0, the history is out of sync with the generation");
+            }
+            life.generation = life.generation.saturating_sub(1);
+        }
+    }
+}
+
+
+/// Redo a single tick (generation) undone by rewinding the simulation.
+pub fn redo_simulation(mut life: ResMut<'_, Life>, mut actions: EventReader<'_, '_, InputAction>) {
+    for action in actions.read() {
+        if let InputAction::RedoSimulation = action {
+            let Some(next_gen) = life.redo.pop_front() else {
+                info!("Nothing to redo");
+                return;
+            };
+            let prev_gen = std::mem::replace(&mut life.cells, next_gen);
+            life.push_history(prev_gen);
+            life.update_max_age();
+            life.generation += 1;
+        }
+    }
+}
+
+
+/// Toggle or set the state of a single cell.
+fn toggle_cell(mut life: ResMut<'_, Life>, mut actions: EventReader<'_, '_, InputAction>) {
+    for action in actions.read() {
+        let (xy, alive) = match action {
+            InputAction::ToggleCell(xy) => (xy, !life.is_alive(*xy)),
+            InputAction::SetCell(xy, alive) => (xy, *alive),
+            _ => continue,
+        };
+
+        if !life.in_bounds(*xy) {
+            debug!("Ignoring edit outside of the world bounds");
+            continue;
+        }
+
+        // Dying cells are replaced by live cells or erased, like dead cells.
+        match (alive, life.cells.get(xy).map(|cell| cell.alive)) {
+            (true, None | Some(false)) => {
+                life.cells.insert(*xy, Cell::default());
+            }
+            (false, Some(_)) => {
+                life.cells.remove(xy);
+            }
+            _ => continue,
+        }
+        life.redo.clear();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use bevy::math::IRect;
+    use bevy::prelude::*;
+
+    use super::{
+        random_soup, wrap, Cell, GenerationRecord, GenerationStats, ImmigrationColor,
+        InitialPattern, Life, Neighborhood, PopulationHistory, Rule, RuleError, RuleVariant,
+        SimulationConfig, Snapshots, StepTimes, Topology,
+    };
+    use crate::config;
+    use crate::patterns::{parse_rle, BUTTERFLY, LIBRARY};
+
+    fn glider() -> Life {
+        let mut life = Life::new(64, 36);
+        life.insert_pattern(
+            [(0, 1), (1, 0), (-1, -1), (0, -1), (1, -1)]
+                .map(|(x, y)| (IVec2::new(x + 5, y - 7), Cell::default())),
+        );
+
+        life
+    }
+
+    fn life_with(cells: &[(i32, i32)]) -> Life {
+        let mut life = Life::new(64, 36);
+        life.insert_pattern(
+            cells
+                .iter()
+                .map(|(x, y)| (IVec2::new(*x, *y), Cell::default())),
+        );
+
+        life
+    }
+
+    /// Translate `cells` so their bounding box starts at the origin.
+    fn normalized(cells: impl IntoIterator<Item = IVec2>) -> Vec<IVec2> {
+        let mut cells = cells.into_iter().collect::<Vec<_>>();
+        let min = cells.iter().fold(IVec2::MAX, |min, xy| min.min(*xy));
+        for xy in &mut cells {
+            *xy -= min;
+        }
+        cells.sort_by_key(|xy| (xy.x, xy.y));
+
+        cells
+    }
+
+    #[test]
+    pub fn test_random_soup() {
+        let bounds = IRect::new(-16, -16, 16, 16);
+        let soup = random_soup(bounds, 42, 0.5, RuleVariant::Standard);
+        assert!(!soup.is_empty());
+        assert!(soup.keys().all(|xy| bounds.contains(*xy)));
+
+        // The same seed generates the same soup, and another seed another soup.
+        assert_eq!(random_soup(bounds, 42, 0.5, RuleVariant::Standard), soup);
+        assert_ne!(random_soup(bounds, 43, 0.5, RuleVariant::Standard), soup);
+
+        assert!(random_soup(bounds, 42, 0.0, RuleVariant::Standard).is_empty());
+    }
+
+    #[test]
+    pub fn test_step_times() {
+        let mut times = StepTimes::default();
+        assert_eq!(times.last(), None);
+        assert_eq!(times.average(), None);
+
+        for ms in 1..=config::sim::STEP_TIME_SAMPLES as u64 + 2 {
+            times.push(Duration::from_millis(ms));
+        }
+        let newest = config::sim::STEP_TIME_SAMPLES as u64 + 2;
+        assert_eq!(times.last(), Some(Duration::from_millis(newest)));
+        // The two oldest durations are forgotten.
+        let oldest = 3;
+        assert_eq!(
+            times.average(),
+            Some(Duration::from_micros((oldest + newest) * 1000 / 2))
+        );
+    }
+
+    #[test]
+    pub fn test_population_history() {
+        let record = |generation| GenerationRecord {
+            generation,
+            population: 3,
+            births: 2,
+            deaths: 1,
+        };
+
+        let mut history = PopulationHistory::default();
+        for generation in 1..=3 {
+            history.push(record(generation));
+        }
+        // Rewinding and advancing again replaces the later generations.
+        history.push(record(2));
+        assert_eq!(history.len(), 2);
+
+        let mut csv = Vec::new();
+        history.write_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "generation,population,births,deaths\n1,3,2,1\n2,3,2,1\n"
+        );
+    }
+
+    #[test]
+    pub fn test_initial_pattern() {
+        let bounds = Life::new(64, 36).bounds;
+        let cells = |pattern: InitialPattern| pattern.cells(bounds, 0, RuleVariant::default());
+
+        assert!(cells(InitialPattern::Empty).is_empty());
+        for (name, rle) in LIBRARY {
+            assert_eq!(
+                cells(InitialPattern::Preset(name)),
+                parse_rle(rle).unwrap(),
+                "{name}"
+            );
+        }
+        assert_eq!(
+            cells(InitialPattern::Cells(vec![IVec2::ZERO, IVec2::X])),
+            [(IVec2::ZERO, Cell::default()), (IVec2::X, Cell::default())]
+        );
+        assert!(!cells(InitialPattern::Random).is_empty());
+
+        // Patterns that can't be loaded fall back to the "Butterfly" pattern.
+        let butterfly = parse_rle(BUTTERFLY).unwrap();
+        assert_eq!(cells(InitialPattern::Preset("Nonexistent")), butterfly);
+        assert_eq!(
+            cells(InitialPattern::File(PathBuf::from("nonexistent.rle"))),
+            butterfly
+        );
+    }
+
+    #[test]
+    pub fn test_snapshots() {
+        let board = |x: i32| [(IVec2::new(x, 0), Cell::default())].into_iter().collect();
+
+        let mut snapshots = Snapshots::default();
+        assert!(snapshots.cycle(true).is_none());
+
+        for x in 0..3 {
+            snapshots.push(board(x));
+        }
+        assert_eq!(snapshots.current(), Some(2));
+
+        // Cycling wraps around in both directions.
+        assert_eq!(snapshots.cycle(true), Some(&board(0)));
+        assert_eq!(snapshots.cycle(false), Some(&board(2)));
+        assert_eq!(snapshots.cycle(false), Some(&board(1)));
+
+        // The oldest snapshots are forgotten.
+        #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+        for x in 3..3 + config::sim::MAX_SNAPSHOTS as i32 {
+            snapshots.push(board(x));
+        }
+        assert_eq!(snapshots.len(), config::sim::MAX_SNAPSHOTS);
+        assert_eq!(snapshots.cycle(true), Some(&board(3)));
+    }
+
+    #[test]
+    pub fn test_to_rle() {
+        let rule = Rule::default();
+        assert_eq!(
+            glider().to_rle(&rule),
+            "x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!"
+        );
+        assert_eq!(
+            Life::new(64, 36).to_rle(&rule),
+            "x = 0, y = 0, rule = B3/S23\n!"
+        );
+
+        let highlife = Rule::parse("B36/S23").unwrap();
+        assert_eq!(
+            glider().to_rle(&highlife),
+            "x = 3, y = 3, rule = B36/S23\nbo$2bo$3o!"
+        );
+    }
+
+    #[test]
+    pub fn test_rle_round_trip() {
+        let mut life = glider();
+        life.insert_pattern([(IVec2::new(-20, 12), Cell::default())]);
+
+        let parsed = parse_rle(&life.to_rle(&Rule::default())).unwrap();
+        assert_eq!(
+            normalized(parsed.into_iter().map(|(xy, _)| xy)),
+            normalized(life.cells.keys().copied())
+        );
+    }
+
+    #[test]
+    pub fn test_set_history_limit() {
+        let mut life = glider();
+        for _ in 0..8 {
+            life.history.push_front(life.cells.clone());
+        }
+
+        life.set_history_limit(3);
+        assert_eq!(life.history.len(), 3);
+        assert_eq!(life.history_limit(), 3);
+
+        life.set_history_limit(16);
+        assert_eq!(life.history.len(), 3);
+    }
+
+    #[test]
+    pub fn test_live_bounds() {
+        assert_eq!(Life::new(64, 36).live_bounds(), None);
+        assert_eq!(glider().live_bounds(), Some(IRect::new(4, -8, 6, -6)));
+    }
+
+    #[test]
+    pub fn test_flood_fill() {
+        // Walled off 3x3 region around the origin.
+        let wall = (-2..=2)
+            .flat_map(|i| [(i, -2), (i, 2), (-2, i), (2, i)])
+            .collect::<Vec<_>>();
+        let life = life_with(&wall);
+
+        let mut region = life.flood_fill(IVec2::ZERO, 9).unwrap();
+        region.sort_by_key(|xy| (xy.x, xy.y));
+        assert_eq!(
+            region,
+            (-1..=1)
+                .flat_map(|x| (-1..=1).map(move |y| IVec2::new(x, y)))
+                .collect::<Vec<_>>()
+        );
+
+        // Live cells and cells out of bounds can't be filled.
+        assert_eq!(life.flood_fill(IVec2::new(2, 0), 64), None);
+        assert_eq!(life.flood_fill(IVec2::new(100, 0), 64), None);
+
+        // Regions over the limit aren't filled at all.
+        assert_eq!(life.flood_fill(IVec2::ZERO, 8), None);
+        assert_eq!(life.flood_fill(IVec2::new(10, 10), 64), None);
+
+        // The world bounds stop the fill.
+        let life = Life::new(4, 4);
+        assert_eq!(
+            life.flood_fill(IVec2::ZERO, 16).map(|region| region.len()),
+            Some(16)
+        );
+    }
+
+    #[test]
+    pub fn test_resize() {
+        let mut life = glider();
+        life.history.push_front(life.cells.clone());
+
+        life.resize(128, 72);
+        assert_eq!(life.bounds.size(), IVec2::new(128, 72));
+        assert_eq!(life.cells.len(), 5);
+
+        // The glider spans x = 4..=6 and y = -8..=-6.
+        life.resize(10, 16);
+        assert_eq!(life.bounds.size(), IVec2::new(10, 16));
+        assert!(life.cells.keys().all(|xy| life.in_bounds(*xy)));
+        assert_eq!(life.cells.len(), 1);
+        assert_eq!(life.history[0].len(), 1);
+
+        // Bounds don't have to be centered on the origin.
+        life.set_bounds(IRect::new(4, -8, 8, 0));
+        assert_eq!(life.bounds.size(), IVec2::new(4, 8));
+        assert_eq!(life.cells.len(), 1);
+        life.set_bounds(IRect::new(0, 0, 10, 10));
+        assert!(life.cells.is_empty());
+    }
+
+    #[test]
+    pub fn test_parse_rule() {
+        let life = Rule::parse("B3/S23").unwrap();
+        assert_eq!(life, Rule::default());
+        assert_eq!(Rule::parse("s23/b3").unwrap(), life);
+
+        let brians_brain = Rule::parse("/2/3").unwrap();
+        assert_eq!(brians_brain.decay_states, 2);
+        assert!(brians_brain.birth[2]);
+        assert!(!brians_brain.survival.contains(&true));
+        assert_eq!(brians_brain.to_string(), "B2/S/C3");
+        assert_eq!(Rule::parse("B2/S/C3").unwrap(), brians_brain);
+
+        assert_eq!(
+            Rule::parse("B2/S/C1"),
+            Err(RuleError::InvalidStates(String::from("1")))
+        );
+        assert_eq!(
+            Rule::parse("B3/S23/B3"),
+            Err(RuleError::Malformed(String::from("B3/S23/B3")))
+        );
+        assert_eq!(
+            Rule::parse("B39/S23"),
+            Err(RuleError::InvalidNeighborCount('9'))
+        );
+        assert_eq!(
+            Rule::parse("B33/S23"),
+            Err(RuleError::DuplicateNeighborCount('3'))
+        );
+        assert_eq!(Rule::parse("B3"), Err(RuleError::MissingSurvival));
+        assert_eq!(
+            Rule::parse("B03/S23"),
+            Err(RuleError::BirthWithoutNeighbors)
+        );
+        assert_eq!(
+            Rule::parse("23/03/3"),
+            Err(RuleError::BirthWithoutNeighbors)
+        );
+
+        for (name, rule) in Rule::PRESETS {
+            assert_eq!(
+                Rule::parse(rule).map(|rule| rule.to_string()).as_deref(),
+                Ok(rule),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_step_decay() {
+        let config = SimulationConfig {
+            rule: Rule::parse("/2/3").unwrap(),
+            ..SimulationConfig::default()
+        };
+
+        // Each live cell dies, and the cells next to both of them are born.
+        let mut life = Life::new(64, 36);
+        life.insert_pattern([(0, 0), (1, 0)].map(|(x, y)| (IVec2::new(x, y), Cell::default())));
+
+        life.advance(&config);
+        let dying = life.cells.values().filter(|cell| !cell.alive).count();
+        assert_eq!((life.population(), dying), (4, 2));
+        assert_eq!(life.cells[&IVec2::new(0, 0)].state, 1);
+
+        // Dying cells vanish and don't count as live neighbors.
+        life.advance(&config);
+        assert!(!life.cells.contains_key(&IVec2::new(0, 0)));
+        assert!(life
+            .cells
+            .values()
+            .all(|cell| cell.alive || cell.state == 1));
+    }
+
+    #[test]
+    pub fn test_cell_queries() {
+        let config = SimulationConfig {
+            rule: Rule::parse("/2/3").unwrap(),
+            ..SimulationConfig::default()
+        };
+
+        let mut life = Life::new(64, 36);
+        life.insert_pattern([(0, 0), (1, 0)].map(|(x, y)| (IVec2::new(x, y), Cell::default())));
+        assert!(life.is_alive(IVec2::new(0, 0)));
+        assert_eq!(life.age_of(IVec2::new(0, 0)), Some(0));
+
+        // Dying cells are neither alive nor have an age.
+        life.advance(&config);
+        assert!(!life.is_alive(IVec2::new(0, 0)));
+        assert_eq!(life.age_of(IVec2::new(0, 0)), None);
+        assert!(life.is_alive(IVec2::new(0, 1)));
+        assert_eq!(life.age_of(IVec2::new(0, 1)), Some(0));
+
+        assert!(!life.is_alive(IVec2::new(10, 10)));
+        assert_eq!(life.age_of(IVec2::new(10, 10)), None);
+    }
+
+    #[test]
+    pub fn test_wrap() {
+        let bounds = IRect::new(-4, -3, 4, 3);
+
+        assert_eq!(wrap(&bounds, IVec2::new(1, -2)), IVec2::new(1, -2));
+        assert_eq!(wrap(&bounds, IVec2::new(4, 3)), IVec2::new(-4, -3));
+        assert_eq!(wrap(&bounds, IVec2::new(-5, -4)), IVec2::new(3, 2));
+
+        // Off by one at either edge: `min` and `max - 1` lie within the bounds, `max` and
+        // `min - 1` don't.
+        assert_eq!(wrap(&bounds, IVec2::new(-4, 0)), IVec2::new(-4, 0));
+        assert_eq!(wrap(&bounds, IVec2::new(3, 0)), IVec2::new(3, 0));
+        assert_eq!(wrap(&bounds, IVec2::new(4, 0)), IVec2::new(-4, 0));
+        assert_eq!(wrap(&bounds, IVec2::new(-5, 0)), IVec2::new(3, 0));
+        assert_eq!(wrap(&bounds, IVec2::new(0, -3)), IVec2::new(0, -3));
+        assert_eq!(wrap(&bounds, IVec2::new(0, 2)), IVec2::new(0, 2));
+        assert_eq!(wrap(&bounds, IVec2::new(0, 3)), IVec2::new(0, -3));
+        assert_eq!(wrap(&bounds, IVec2::new(0, -4)), IVec2::new(0, 2));
+
+        // Diagonal neighbors of each corner wrap to the opposite corner.
+        for (outside, corner) in [
+            ((-5, -4), (3, 2)),
+            ((4, -4), (-4, 2)),
+            ((-5, 3), (3, -3)),
+            ((4, 3), (-4, -3)),
+        ] {
+            assert_eq!(wrap(&bounds, IVec2::from(outside)), IVec2::from(corner));
+        }
+
+        // Several widths and heights away.
+        assert_eq!(
+            wrap(&bounds, IVec2::new(1 + 8 * 3, -2 - 6 * 5)),
+            IVec2::new(1, -2)
+        );
+        assert_eq!(
+            wrap(&bounds, IVec2::new(-5 - 8 * 2, 3 + 6 * 4)),
+            IVec2::new(3, -3)
+        );
+    }
+
+    #[test]
+    pub fn test_live_neighbors() {
+        let life = life_with(&[(0, 0), (1, 0), (2, 0), (1, 1)]);
+        let config = SimulationConfig::default();
+
+        assert_eq!(life.live_neighbors(&config, IVec2::new(1, 0)), 3);
+        assert_eq!(life.live_neighbors(&config, IVec2::new(0, 0)), 2);
+        assert_eq!(life.live_neighbors(&config, IVec2::new(1, -1)), 3);
+        assert_eq!(life.live_neighbors(&config, IVec2::new(5, 5)), 0);
+
+        let config = SimulationConfig {
+            neighborhood: Neighborhood::VonNeumann,
+            ..SimulationConfig::default()
+        };
+        assert_eq!(life.live_neighbors(&config, IVec2::new(0, 0)), 1);
+    }
+
+    #[test]
+    pub fn test_block() {
+        let life = life_with(&[(0, 0), (1, 0), (0, 1), (1, 1)]);
+
+        let next_gen = life.step(&SimulationConfig::default());
+        assert_eq!(
+            normalized(next_gen.keys().copied()),
+            normalized(life.cells.keys().copied())
+        );
+        assert!(next_gen.values().all(|cell| cell.alive && cell.age == 1));
+    }
+
+    #[test]
+    pub fn test_blinker() {
+        let horizontal = normalized([(-1, 0), (0, 0), (1, 0)].map(IVec2::from));
+        let vertical = normalized([(0, -1), (0, 0), (0, 1)].map(IVec2::from));
+        let config = SimulationConfig::default();
+
+        let mut life = life_with(&[(-1, 0), (0, 0), (1, 0)]);
+        life.cells = life.step(&config);
+        assert_eq!(normalized(life.cells.keys().copied()), vertical);
+        life.cells = life.step(&config);
+        assert_eq!(normalized(life.cells.keys().copied()), horizontal);
+    }
+
+    #[test]
+    pub fn test_immigration() {
+        let config = SimulationConfig {
+            variant: RuleVariant::Immigration,
+            ..SimulationConfig::default()
+        };
+
+        let mut life = Life::new(64, 36);
+        life.insert_pattern([
+            (IVec2::new(-1, 0), Cell::with_color(ImmigrationColor::B)),
+            (IVec2::new(0, 0), Cell::with_color(ImmigrationColor::A)),
+            (IVec2::new(1, 0), Cell::with_color(ImmigrationColor::B)),
+        ]);
+
+        // The newborn cells take the majority color of their parents, and the survivor keeps its.
+        let next_gen = life.step(&config);
+        assert_eq!(next_gen[&IVec2::new(0, 1)].color, ImmigrationColor::B);
+        assert_eq!(next_gen[&IVec2::new(0, -1)].color, ImmigrationColor::B);
+        assert_eq!(next_gen[&IVec2::new(0, 0)].color, ImmigrationColor::A);
+
+        // Without the variant, all cells are born alike.
+        let next_gen = life.step(&SimulationConfig::default());
+        assert_eq!(next_gen[&IVec2::new(0, 1)].color, ImmigrationColor::A);
+    }
+
+    #[test]
+    pub fn test_unbounded() {
+        let config = SimulationConfig {
+            topology: Topology::Unbounded,
+            ..SimulationConfig::default()
+        };
+
+        // The glider flies past the initial bounds without wrapping, followed by the bounds.
+        let mut life = life_with(&[(0, 1), (1, 0), (-1, -1), (0, -1), (1, -1)]);
+        let initial = life.cells.keys().copied().collect::<Vec<_>>();
+        for _ in 0..400 {
+            life.advance(&config);
+        }
+        assert_eq!(life.cells.len(), 5);
+        assert!(initial
+            .iter()
+            .all(|xy| life.cells.contains_key(&(*xy + IVec2::new(100, -100)))));
+        assert!(life.cells.keys().all(|xy| life.in_bounds(*xy)));
+        assert!(life.cells.keys().all(|xy| xy.x >= 99 && xy.y <= -99));
+
+        // The bounds only change once the live cells come near an edge.
+        let bounds = life.bounds;
+        assert!(!life.fit_bounds());
+        assert_eq!(life.bounds, bounds);
+    }
+
+    #[test]
+    pub fn test_advance() {
+        let mut life = glider();
+        let initial = life.cells.keys().copied().collect::<Vec<_>>();
+
+        // A glider translates by one cell diagonally every four generations. Every generation,
+        // two cells are born and two die.
+        let config = SimulationConfig::default();
+        for generation in 1..=4 {
+            let delta = life.advance(&config);
+            assert_eq!(
+                delta.stats(),
+                GenerationStats {
+                    births: 2,
+                    deaths: 2
+                }
+            );
+            assert_eq!(delta.generation, generation);
+            assert!(delta
+                .born
+                .iter()
+                .all(|xy| life.cells.get(xy).is_some_and(|cell| cell.alive)));
+            assert!(delta.died.iter().all(|xy| !life.cells.contains_key(xy)));
+        }
+
+        assert_eq!(life.generation, 4);
+        assert_eq!(life.history.len(), 4);
+
+        let mut expected = initial
+            .iter()
+            .map(|xy| *xy + IVec2::new(1, -1))
+            .collect::<Vec<_>>();
+        let mut cells = life.cells.keys().copied().collect::<Vec<_>>();
+        expected.sort_by_key(|xy| (xy.x, xy.y));
+        cells.sort_by_key(|xy| (xy.x, xy.y));
+        assert_eq!(cells, expected);
+
+        // The oldest generation in the history is the initial one.
+        let mut oldest = life
+            .history
+            .back()
+            .unwrap()
+            .keys()
+            .copied()
+            .collect::<Vec<_>>();
+        let mut initial = initial;
+        oldest.sort_by_key(|xy| (xy.x, xy.y));
+        initial.sort_by_key(|xy| (xy.x, xy.y));
+        assert_eq!(oldest, initial);
+    }
+}
@@ -2,26 +2,166 @@
 // Copyright (c) 2023 Martin Green. All rights reserved.
 //
 
+use ahash::AHashSet as HashSet;
+use bevy::math::IRect;
 use bevy::prelude::*;
 use bevy::window::PrimaryWindow;
 
 use crate::camera::MainCamera;
-use crate::config::cells::{SPRITE_SIZE, SPRITE_WORLD_OFFSET};
-use crate::game::{GameLogicSet, SimulationConfig, SimulationUpdateTimer};
-use crate::{AppState, WindowFocused};
+use crate::game::{GameLogicSet, Life, SimulationConfig, SimulationUpdateTimer};
+use crate::ui::PointerOverUi;
+use crate::{config, AppState, WindowFocused};
 
 
+pub mod bindings;
+
+use bindings::{Binding, KeyBindings, KeyChord, PendingRebind};
+
+
+/// Position of the cursor in the world, offset so cells are centered on multiples of the sprite
+/// size.
 #[derive(Default, Resource, Deref, DerefMut)]
-struct CursorWorldPosition(Vec2);
+pub struct CursorWorldPosition(Vec2);
+
+impl CursorWorldPosition {
+    /// Return the cursor position for `world`, a position in world space.
+    pub fn from_world(world: Vec2) -> Self {
+        Self(world - config::cells::sprite_world_offset())
+    }
+
+    /// Return the cell under the cursor.
+    pub fn cell(&self) -> IVec2 {
+        cell_at(self.0, config::cells::sprite_size())
+    }
+}
+
+
+/// Return the cell centered nearest to `position`, for cells of `sprite_size`. Sides don't have to
+/// be equal.
+fn cell_at(position: Vec2, sprite_size: Vec2) -> IVec2 {
+    (position / sprite_size).round().as_ivec2()
+}
+
+
+/// Radius of the square brush painting and erasing cells. A radius of 0 paints a single cell.
+#[derive(Default, Resource, Deref, DerefMut)]
+pub struct BrushRadius(pub u32);
+
+
+/// Mirroring of edited cells across the center axes of the world.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Resource)]
+pub enum DrawSymmetry {
+    #[default]
+    None,
+    /// Mirror left to right.
+    Horizontal,
+    /// Mirror top to bottom.
+    Vertical,
+    /// Mirror left to right and top to bottom.
+    Quad,
+}
+
+impl DrawSymmetry {
+    /// Return `xy` and its mirror images across the center axes of `bounds`, without duplicates.
+    pub fn mirror(self, bounds: IRect, xy: IVec2) -> Vec<IVec2> {
+        // `bounds.max` is exclusive.
+        let mirrored = bounds.min + bounds.max - IVec2::ONE - xy;
+
+        let mut cells = match self {
+            Self::None => vec![xy],
+            Self::Horizontal => vec![xy, IVec2::new(mirrored.x, xy.y)],
+            Self::Vertical => vec![xy, IVec2::new(xy.x, mirrored.y)],
+            Self::Quad => vec![
+                xy,
+                IVec2::new(mirrored.x, xy.y),
+                IVec2::new(xy.x, mirrored.y),
+                mirrored,
+            ],
+        };
+        cells.sort_by_key(|xy| (xy.x, xy.y));
+        cells.dedup();
+
+        cells
+    }
+}
+
+
+/// First cell of the line being drawn with the line tool, waiting for the click on the last cell.
+#[derive(Default, Resource, Deref)]
+pub struct LineStart(Option<IVec2>);
+
+
+/// Mouse drag painting cells.
+#[derive(Default, Resource)]
+struct PaintStroke {
+    /// Most recently painted cell, or `None` if no mouse button is held down.
+    last: Option<IVec2>,
+}
 
 
 #[derive(Event)]
 pub enum InputAction {
     ToggleCell(IVec2),
+    /// Set the cell at the position alive or dead, regardless of its current state.
+    SetCell(IVec2, bool),
     PauseSimulation,
     UnpauseSimulation,
     AdvanceSimulation,
+    /// Advance the simulation many ticks (generations) at once.
+    StepMany(u32),
     RewindSimulation,
+    RedoSimulation,
+    /// Replace the board with a random soup, optionally from the next seed.
+    RandomizeBoard {
+        density: f32,
+        reroll: bool,
+    },
+    ClearBoard,
+    /// Replace the board with the initial pattern, resetting the generation and the history.
+    ResetSimulation,
+    /// Treat the current cells as generation 0, forgetting the history but keeping the cells.
+    ResetGeneration,
+    /// Resize the world to about `width` by `height` cells, centered on the origin.
+    ResizeBoard {
+        width: u32,
+        height: u32,
+    },
+    /// Move the corners of the world to the given bounds, keeping the cells within.
+    SetBounds(IRect),
+    SaveState,
+    LoadState,
+    /// Export the statistics of the recorded generations as CSV.
+    ExportStats,
+    Screenshot,
+    /// Zoom the camera in by this many steps of `config::camera::ZOOM_STEP`, or out if negative,
+    /// keeping the center of the view in place.
+    ZoomCamera(i32),
+    ResetCamera,
+    ToggleGrid,
+    ToggleHelp,
+    /// Show / hide the frame rate and the measured tick rate.
+    ToggleStats,
+    /// Select the cells within the rectangle, both corners inclusive.
+    Select(IRect),
+    CopySelection,
+    /// Copy a pattern in the RLE format to the clipboard.
+    CopyPattern(&'static str),
+    /// Set the empty region around the cell alive.
+    FloodFill(IVec2),
+    /// Paste the clipboard with its origin at the cell.
+    Paste(IVec2),
+    /// Rotate the clipboard 90° counterclockwise.
+    RotateClipboard,
+    /// Mirror the clipboard horizontally or vertically.
+    FlipClipboard {
+        horizontal: bool,
+    },
+    /// Keep the board in memory as a snapshot.
+    TakeSnapshot,
+    /// Replace the board with the next or previous snapshot.
+    CycleSnapshot {
+        next: bool,
+    },
 }
 
 
@@ -31,17 +171,49 @@ pub struct InputPlugin;
 impl Plugin for InputPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<CursorWorldPosition>()
+            .init_resource::<PaintStroke>()
+            .init_resource::<LineStart>()
+            .init_resource::<BrushRadius>()
+            .init_resource::<DrawSymmetry>()
+            .init_resource::<KeyBindings>()
+            .init_resource::<PendingRebind>()
             .add_event::<InputAction>()
             .add_systems(
                 Update,
                 (
-                    (get_cursor_world_position, toggle_cell_on_lmb).chain(),
                     (
+                        get_cursor_world_position,
+                        draw_line_on_click,
+                        flood_fill_on_click,
+                        edit_cell_on_click,
+                        paint_cells_on_drag,
+                        select_region_on_drag,
+                        toggle_cell_on_tap,
+                    )
+                        .chain(),
+                    (
+                        rebind_on_key,
                         (
                             toggle_pause_simulation_on_key,
                             advance_simulation_on_key,
                             rewind_simulation_on_key,
                             change_simulation_rate_on_key,
+                            randomize_board_on_key,
+                            clear_board_on_key,
+                            reset_simulation_on_key,
+                            save_state_on_key,
+                            load_state_on_key,
+                            export_stats_on_key,
+                            screenshot_on_key,
+                            zoom_camera_on_key,
+                            reset_camera_on_key,
+                            toggle_grid_on_key,
+                            toggle_help_on_key,
+                            toggle_stats_on_key,
+                            copy_selection_on_key,
+                            paste_on_key,
+                            transform_clipboard_on_key,
+                            snapshot_on_key,
                         ),
                         toggle_simulation_paused,
                     )
@@ -53,27 +225,63 @@ impl Plugin for InputPlugin {
 }
 
 
+/// Bind the next key press to the pending binding, if any. The key press is consumed, so it doesn't
+/// trigger the action previously bound to the key. `Escape` cancels rebinding.
+fn rebind_on_key(
+    mut keys: ResMut<'_, ButtonInput<KeyCode>>,
+    mut pending: ResMut<'_, PendingRebind>,
+    mut bindings: ResMut<'_, KeyBindings>,
+) {
+    const MODIFIER_KEYS: [KeyCode; 4] = [
+        KeyCode::ControlLeft,
+        KeyCode::ControlRight,
+        KeyCode::ShiftLeft,
+        KeyCode::ShiftRight,
+    ];
+
+    let Some(binding) = **pending else {
+        return;
+    };
+    let Some(key) = keys
+        .get_just_pressed()
+        .find(|key| !MODIFIER_KEYS.contains(key))
+        .copied()
+    else {
+        return;
+    };
+
+    if key != KeyCode::Escape {
+        let chord = KeyChord {
+            key,
+            ctrl: ctrl_pressed(&keys),
+            shift: shift_pressed(&keys),
+        };
+        info!("Bound {chord} to {binding:?}");
+        bindings.set(binding, chord);
+    }
+
+    keys.clear_just_pressed(key);
+    **pending = None;
+}
+
+
 /// Pause / unpause the simulation on key press.
 fn toggle_pause_simulation_on_key(
     keys: Res<'_, ButtonInput<KeyCode>>,
+    bindings: Res<'_, KeyBindings>,
     state: Res<'_, State<AppState>>,
     mut actions: EventWriter<'_, InputAction>,
 ) {
-    const PAUSE_KEYS: [KeyCode; 2] = [KeyCode::Space, KeyCode::KeyP];
-
-    for key in PAUSE_KEYS {
-        if keys.just_pressed(key) {
-            // Pause when running and unpause when paused.
-            match state.get() {
-                AppState::Running => {
-                    actions.send(InputAction::PauseSimulation);
-                }
-                AppState::Paused => {
-                    actions.send(InputAction::UnpauseSimulation);
-                }
-                _ => {}
+    if bindings.just_pressed(Binding::Pause, &keys) {
+        // Pause when running and unpause when paused.
+        match state.get() {
+            AppState::Running => {
+                actions.send(InputAction::PauseSimulation);
             }
-            break;
+            AppState::Paused => {
+                actions.send(InputAction::UnpauseSimulation);
+            }
+            _ => {}
         }
     }
 }
@@ -82,16 +290,12 @@ fn toggle_pause_simulation_on_key(
 /// Advance the simulation by a single tick (generation) on key press.
 fn advance_simulation_on_key(
     keys: Res<'_, ButtonInput<KeyCode>>,
+    bindings: Res<'_, KeyBindings>,
     mut actions: EventWriter<'_, InputAction>,
 ) {
-    const ADV_SIM_BINDINGS: [KeyCode; 1] = [KeyCode::BracketRight];
-
-    for binding in ADV_SIM_BINDINGS {
-        if keys.just_pressed(binding) {
-            actions.send(InputAction::PauseSimulation);
-            actions.send(InputAction::AdvanceSimulation);
-            break;
-        }
+    if bindings.just_pressed(Binding::Advance, &keys) {
+        actions.send(InputAction::PauseSimulation);
+        actions.send(InputAction::AdvanceSimulation);
     }
 }
 
@@ -99,20 +303,245 @@ fn advance_simulation_on_key(
 /// Rewind the simulation by a single tick (generation) on key press.
 fn rewind_simulation_on_key(
     keys: Res<'_, ButtonInput<KeyCode>>,
+    bindings: Res<'_, KeyBindings>,
     mut actions: EventWriter<'_, InputAction>,
 ) {
-    const RWD_SIM_BINDINGS: [KeyCode; 1] = [KeyCode::BracketLeft];
+    if bindings.just_pressed(Binding::Rewind, &keys) {
+        actions.send(InputAction::PauseSimulation);
+        actions.send(InputAction::RewindSimulation);
+    }
+}
 
-    for bindings in RWD_SIM_BINDINGS {
-        if keys.just_pressed(bindings) {
-            actions.send(InputAction::PauseSimulation);
-            actions.send(InputAction::RewindSimulation);
-            break;
-        }
+
+/// Replace the board with a random soup on key press.
+fn randomize_board_on_key(
+    keys: Res<'_, ButtonInput<KeyCode>>,
+    bindings: Res<'_, KeyBindings>,
+    mut actions: EventWriter<'_, InputAction>,
+) {
+    if bindings.just_pressed(Binding::Randomize, &keys) {
+        actions.send(InputAction::RandomizeBoard {
+            density: config::sim::DEFAULT_SOUP_DENSITY,
+            reroll: true,
+        });
+    }
+}
+
+
+/// Clear the board on key press.
+fn clear_board_on_key(
+    keys: Res<'_, ButtonInput<KeyCode>>,
+    bindings: Res<'_, KeyBindings>,
+    mut actions: EventWriter<'_, InputAction>,
+) {
+    if bindings.just_pressed(Binding::Clear, &keys) {
+        actions.send(InputAction::ClearBoard);
+    }
+}
+
+
+/// Reset the simulation to the initial pattern on key press.
+fn reset_simulation_on_key(
+    keys: Res<'_, ButtonInput<KeyCode>>,
+    bindings: Res<'_, KeyBindings>,
+    mut actions: EventWriter<'_, InputAction>,
+) {
+    if bindings.just_pressed(Binding::Reset, &keys) {
+        actions.send(InputAction::ResetSimulation);
+    }
+}
+
+
+/// Save the simulation state on key press.
+fn save_state_on_key(
+    keys: Res<'_, ButtonInput<KeyCode>>,
+    bindings: Res<'_, KeyBindings>,
+    mut actions: EventWriter<'_, InputAction>,
+) {
+    if bindings.just_pressed(Binding::Save, &keys) {
+        actions.send(InputAction::SaveState);
+    }
+}
+
+
+/// Export the statistics of the recorded generations on key press.
+fn export_stats_on_key(
+    keys: Res<'_, ButtonInput<KeyCode>>,
+    bindings: Res<'_, KeyBindings>,
+    mut actions: EventWriter<'_, InputAction>,
+) {
+    if bindings.just_pressed(Binding::ExportStats, &keys) {
+        actions.send(InputAction::ExportStats);
+    }
+}
+
+
+/// Load the simulation state on key press.
+fn load_state_on_key(
+    keys: Res<'_, ButtonInput<KeyCode>>,
+    bindings: Res<'_, KeyBindings>,
+    mut actions: EventWriter<'_, InputAction>,
+) {
+    if bindings.just_pressed(Binding::Load, &keys) {
+        actions.send(InputAction::LoadState);
+    }
+}
+
+
+/// Take a screenshot on key press.
+fn screenshot_on_key(
+    keys: Res<'_, ButtonInput<KeyCode>>,
+    bindings: Res<'_, KeyBindings>,
+    mut actions: EventWriter<'_, InputAction>,
+) {
+    if bindings.just_pressed(Binding::Screenshot, &keys) {
+        actions.send(InputAction::Screenshot);
+    }
+}
+
+
+/// Zoom the camera in or out on key press.
+fn zoom_camera_on_key(
+    keys: Res<'_, ButtonInput<KeyCode>>,
+    bindings: Res<'_, KeyBindings>,
+    mut actions: EventWriter<'_, InputAction>,
+) {
+    if bindings.just_pressed(Binding::ZoomIn, &keys) {
+        actions.send(InputAction::ZoomCamera(1));
+    }
+    if bindings.just_pressed(Binding::ZoomOut, &keys) {
+        actions.send(InputAction::ZoomCamera(-1));
+    }
+}
+
+
+/// Reset the camera on key press.
+fn reset_camera_on_key(
+    keys: Res<'_, ButtonInput<KeyCode>>,
+    bindings: Res<'_, KeyBindings>,
+    mut actions: EventWriter<'_, InputAction>,
+) {
+    if bindings.just_pressed(Binding::ResetCamera, &keys) {
+        actions.send(InputAction::ResetCamera);
+    }
+}
+
+
+/// Show / hide the grid on key press.
+fn toggle_grid_on_key(
+    keys: Res<'_, ButtonInput<KeyCode>>,
+    bindings: Res<'_, KeyBindings>,
+    mut actions: EventWriter<'_, InputAction>,
+) {
+    if bindings.just_pressed(Binding::ToggleGrid, &keys) {
+        actions.send(InputAction::ToggleGrid);
+    }
+}
+
+
+/// Show / hide the help window on key press.
+fn toggle_help_on_key(
+    keys: Res<'_, ButtonInput<KeyCode>>,
+    bindings: Res<'_, KeyBindings>,
+    mut actions: EventWriter<'_, InputAction>,
+) {
+    if bindings.just_pressed(Binding::ToggleHelp, &keys) {
+        actions.send(InputAction::ToggleHelp);
+    }
+}
+
+
+/// Show / hide the frame rate and tick rate on key press.
+fn toggle_stats_on_key(
+    keys: Res<'_, ButtonInput<KeyCode>>,
+    bindings: Res<'_, KeyBindings>,
+    mut actions: EventWriter<'_, InputAction>,
+) {
+    if bindings.just_pressed(Binding::ToggleStats, &keys) {
+        actions.send(InputAction::ToggleStats);
+    }
+}
+
+
+/// Copy the selection to the clipboard on key press.
+fn copy_selection_on_key(
+    keys: Res<'_, ButtonInput<KeyCode>>,
+    bindings: Res<'_, KeyBindings>,
+    mut actions: EventWriter<'_, InputAction>,
+) {
+    if bindings.just_pressed(Binding::Copy, &keys) {
+        actions.send(InputAction::CopySelection);
+    }
+}
+
+
+/// Paste the clipboard at the cell under the cursor on key press.
+fn paste_on_key(
+    keys: Res<'_, ButtonInput<KeyCode>>,
+    bindings: Res<'_, KeyBindings>,
+    mouse_position: Res<'_, CursorWorldPosition>,
+    mut actions: EventWriter<'_, InputAction>,
+) {
+    if bindings.just_pressed(Binding::Paste, &keys) {
+        actions.send(InputAction::Paste(mouse_position.cell()));
+    }
+}
+
+
+/// Rotate or flip the clipboard on key press.
+fn transform_clipboard_on_key(
+    keys: Res<'_, ButtonInput<KeyCode>>,
+    bindings: Res<'_, KeyBindings>,
+    mut actions: EventWriter<'_, InputAction>,
+) {
+    if bindings.just_pressed(Binding::RotateClipboard, &keys) {
+        actions.send(InputAction::RotateClipboard);
+    }
+    if bindings.just_pressed(Binding::FlipClipboardHorizontally, &keys) {
+        actions.send(InputAction::FlipClipboard { horizontal: true });
+    }
+    if bindings.just_pressed(Binding::FlipClipboardVertically, &keys) {
+        actions.send(InputAction::FlipClipboard { horizontal: false });
+    }
+}
+
+
+/// Take a snapshot, or load the next or previous snapshot, on key press.
+fn snapshot_on_key(
+    keys: Res<'_, ButtonInput<KeyCode>>,
+    bindings: Res<'_, KeyBindings>,
+    mut actions: EventWriter<'_, InputAction>,
+) {
+    if bindings.just_pressed(Binding::TakeSnapshot, &keys) {
+        actions.send(InputAction::TakeSnapshot);
+    }
+    if bindings.just_pressed(Binding::PreviousSnapshot, &keys) {
+        actions.send(InputAction::CycleSnapshot { next: false });
+    }
+    if bindings.just_pressed(Binding::NextSnapshot, &keys) {
+        actions.send(InputAction::CycleSnapshot { next: true });
     }
 }
 
 
+/// Return `true` if either `Ctrl` key is held down.
+pub fn ctrl_pressed(keys: &ButtonInput<KeyCode>) -> bool {
+    keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+}
+
+
+/// Return `true` if the key of a tool handling its own clicks, such as the line tool, is held down.
+fn tool_pressed(bindings: &KeyBindings, keys: &ButtonInput<KeyCode>) -> bool {
+    bindings.pressed(Binding::DrawLine, keys) || bindings.pressed(Binding::FloodFill, keys)
+}
+
+
+/// Return `true` if either `Shift` key is held down.
+fn shift_pressed(keys: &ButtonInput<KeyCode>) -> bool {
+    keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight])
+}
+
+
 /// Pause / unpause the simulation.
 fn toggle_simulation_paused(
     state: Res<'_, State<AppState>>,
@@ -152,64 +581,403 @@ fn get_cursor_world_position(
         return;
     };
 
-    // @NOTE: Sprites are offset by `OFFSET`. We need to offset the cursor position by
-    // `OFFSET.neg()`.
-    if let Some(position) = window.cursor_position().and_then(|cursor| {
-        camera
-            .viewport_to_world_2d(transform, cursor)
-            .map(|v| v + -SPRITE_WORLD_OFFSET)
-    }) {
-        *mouse_position = CursorWorldPosition(position);
+    if let Some(position) = window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(transform, cursor))
+    {
+        *mouse_position = CursorWorldPosition::from_world(position);
+    }
+}
+
+/// While the line tool key is held down, set the cells on the line between two left-clicked cells
+/// alive. Releasing the key forgets the first cell.
+fn draw_line_on_click(
+    keys: Res<'_, ButtonInput<KeyCode>>,
+    bindings: Res<'_, KeyBindings>,
+    buttons: Res<'_, ButtonInput<MouseButton>>,
+    mouse_position: Res<'_, CursorWorldPosition>,
+    mut start: ResMut<'_, LineStart>,
+    brush: Res<'_, BrushRadius>,
+    symmetry: Res<'_, DrawSymmetry>,
+    life: Res<'_, Life>,
+    mut actions: EventWriter<'_, InputAction>,
+) {
+    if !bindings.pressed(Binding::DrawLine, &keys) {
+        if start.is_some() {
+            start.0 = None;
+        }
+        return;
+    }
+    if !buttons.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    let xy = mouse_position.cell();
+    match start.0.take() {
+        Some(from) => {
+            debug!("Drawing line from {from:?} to {xy:?}");
+            send_brush(
+                &mut actions,
+                **brush,
+                *symmetry,
+                life.bounds,
+                line_cells(from, xy),
+                true,
+            );
+        }
+        None => start.0 = Some(xy),
     }
 }
 
-fn toggle_cell_on_lmb(
+
+/// Fill the empty region around the left-clicked cell while the fill tool key is held down.
+fn flood_fill_on_click(
+    keys: Res<'_, ButtonInput<KeyCode>>,
+    bindings: Res<'_, KeyBindings>,
     buttons: Res<'_, ButtonInput<MouseButton>>,
     mouse_position: Res<'_, CursorWorldPosition>,
+    mut actions: EventWriter<'_, InputAction>,
+) {
+    if bindings.pressed(Binding::FloodFill, &keys) && buttons.just_pressed(MouseButton::Left) {
+        actions.send(InputAction::FloodFill(mouse_position.cell()));
+    }
+}
+
+
+/// Set the cell under the cursor alive on left click and dead on right click. `Ctrl` + left click
+/// toggles the cell instead.
+fn edit_cell_on_click(
+    keys: Res<'_, ButtonInput<KeyCode>>,
+    bindings: Res<'_, KeyBindings>,
+    buttons: Res<'_, ButtonInput<MouseButton>>,
+    mouse_position: Res<'_, CursorWorldPosition>,
+    brush: Res<'_, BrushRadius>,
+    symmetry: Res<'_, DrawSymmetry>,
+    life: Res<'_, Life>,
     mut ev_focused: EventReader<'_, '_, WindowFocused>,
     mut actions: EventWriter<'_, InputAction>,
 ) {
-    if buttons.just_pressed(MouseButton::Left) {
-        // Ignore input that caused the window to receive focus.
-        for event in ev_focused.read() {
-            if event.focused {
-                info!("Ignoring input due to receiving focus");
+    let left = buttons.just_pressed(MouseButton::Left);
+    let right = buttons.just_pressed(MouseButton::Right);
+
+    // `Shift` + left click starts a selection, and tools handle their own clicks.
+    if !(left || right) || (left && shift_pressed(&keys)) || tool_pressed(&bindings, &keys) {
+        return;
+    }
+
+    // Ignore input that caused the window to receive focus.
+    for event in ev_focused.read() {
+        if event.focused {
+            info!("Ignoring input due to receiving focus");
+            return;
+        }
+    }
+
+    let xy = mouse_position.cell();
+    debug!("Clicked {xy:?}");
+
+    if left && ctrl_pressed(&keys) {
+        for xy in symmetry.mirror(life.bounds, xy) {
+            actions.send(InputAction::ToggleCell(xy));
+        }
+    } else {
+        send_brush(&mut actions, **brush, *symmetry, life.bounds, [xy], left);
+    }
+}
+
+
+/// Toggle the tapped cell. Touches that move or are part of a multi-finger gesture, such as
+/// panning the camera, don't count as taps.
+fn toggle_cell_on_tap(
+    touches: Res<'_, Touches>,
+    pointer_over_ui: Res<'_, PointerOverUi>,
+    q_camera: Query<'_, '_, (&Camera, &GlobalTransform), With<MainCamera>>,
+    symmetry: Res<'_, DrawSymmetry>,
+    life: Res<'_, Life>,
+    mut gesture: Local<'_, bool>,
+    mut actions: EventWriter<'_, InputAction>,
+) {
+    if touches.iter().count() > 1 {
+        *gesture = true;
+    }
+
+    for touch in touches.iter_just_released() {
+        if *gesture || **pointer_over_ui || touch.distance().length() > config::touch::TAP_DISTANCE
+        {
+            continue;
+        }
+
+        let Some((camera, transform)) = q_camera.get_single().ok() else {
+            warn!("No main camera");
+            return;
+        };
+        let Some(position) = camera.viewport_to_world_2d(transform, touch.position()) else {
+            continue;
+        };
+
+        let xy = CursorWorldPosition::from_world(position).cell();
+        debug!("Tapped {xy:?}");
+        for xy in symmetry.mirror(life.bounds, xy) {
+            actions.send(InputAction::ToggleCell(xy));
+        }
+    }
+
+    // The gesture ends once all fingers are lifted.
+    if touches.iter().next().is_none() {
+        *gesture = false;
+    }
+}
+
+
+/// Paint cells alive while dragging with the left mouse button held down, and dead while dragging
+/// with the right mouse button held down. Cells between the previous and the current cursor
+/// position are painted too, so fast drags don't leave gaps.
+fn paint_cells_on_drag(
+    keys: Res<'_, ButtonInput<KeyCode>>,
+    bindings: Res<'_, KeyBindings>,
+    buttons: Res<'_, ButtonInput<MouseButton>>,
+    mouse_position: Res<'_, CursorWorldPosition>,
+    mut stroke: ResMut<'_, PaintStroke>,
+    brush: Res<'_, BrushRadius>,
+    symmetry: Res<'_, DrawSymmetry>,
+    life: Res<'_, Life>,
+    mut ev_focused: EventReader<'_, '_, WindowFocused>,
+    mut actions: EventWriter<'_, InputAction>,
+) {
+    let gained_focus = ev_focused.read().filter(|event| event.focused).count() > 0;
+
+    let alive = if buttons.pressed(MouseButton::Left) {
+        true
+    } else if buttons.pressed(MouseButton::Right) {
+        false
+    } else {
+        stroke.last = None;
+        return;
+    };
+
+    let xy = mouse_position.cell();
+    match stroke.last {
+        Some(last) if last == xy => return,
+        Some(last) => {
+            send_brush(
+                &mut actions,
+                **brush,
+                *symmetry,
+                life.bounds,
+                line_cells(last, xy).into_iter().skip(1),
+                alive,
+            );
+        }
+        // The cell under the cursor is edited on pressing the mouse button.
+        None => {
+            // Strokes start on pressing a mouse button, but not if that caused the window to
+            // receive focus, started a selection, toggled a cell, or used a tool.
+            let pressed = buttons.any_just_pressed([MouseButton::Left, MouseButton::Right]);
+            if !pressed
+                || gained_focus
+                || (alive && shift_pressed(&keys))
+                || (alive && ctrl_pressed(&keys))
+                || tool_pressed(&bindings, &keys)
+            {
                 return;
             }
         }
+    }
+    stroke.last = Some(xy);
+}
 
-        #[allow(clippy::cast_possible_truncation)]
-        let xy = IVec2::new(
-            (mouse_position.x / SPRITE_SIZE.x).round() as i32,
-            (mouse_position.y / SPRITE_SIZE.y).round() as i32,
-        );
 
-        debug!("Clicked {xy:?}");
-        actions.send(InputAction::ToggleCell(xy));
+/// Select a rectangular region of cells while dragging with `Shift` + the left mouse button.
+fn select_region_on_drag(
+    keys: Res<'_, ButtonInput<KeyCode>>,
+    buttons: Res<'_, ButtonInput<MouseButton>>,
+    mouse_position: Res<'_, CursorWorldPosition>,
+    mut drag: Local<'_, Option<(IVec2, IVec2)>>,
+    mut actions: EventWriter<'_, InputAction>,
+) {
+    if !buttons.pressed(MouseButton::Left) {
+        *drag = None;
+        return;
+    }
+
+    let xy = mouse_position.cell();
+    match *drag {
+        Some((_, corner)) if corner == xy => return,
+        Some((anchor, _)) => *drag = Some((anchor, xy)),
+        None if buttons.just_pressed(MouseButton::Left) && shift_pressed(&keys) => {
+            *drag = Some((xy, xy));
+        }
+        None => return,
+    }
+
+    if let Some((anchor, corner)) = *drag {
+        actions.send(InputAction::Select(IRect::from_corners(anchor, corner)));
     }
 }
 
 
+/// Set the cells covered by the brush centered on each of `centers`, and their mirror images,
+/// alive or dead.
+fn send_brush(
+    actions: &mut EventWriter<'_, InputAction>,
+    radius: u32,
+    symmetry: DrawSymmetry,
+    bounds: IRect,
+    centers: impl IntoIterator<Item = IVec2>,
+    alive: bool,
+) {
+    #[allow(clippy::cast_possible_wrap)]
+    let radius = radius as i32;
+
+    // Brushes around neighboring centers overlap.
+    let mut painted = HashSet::new();
+    for center in centers {
+        for y in -radius..=radius {
+            for x in -radius..=radius {
+                for xy in symmetry.mirror(bounds, center + IVec2::new(x, y)) {
+                    if painted.insert(xy) {
+                        actions.send(InputAction::SetCell(xy, alive));
+                    }
+                }
+            }
+        }
+    }
+}
+
+
+/// Return the cells on the line from `from` to `to`, both inclusive, using Bresenham's line
+/// algorithm.
+pub fn line_cells(from: IVec2, to: IVec2) -> Vec<IVec2> {
+    let delta = (to - from).abs();
+    let step = (to - from).signum();
+
+    let mut cells = vec![from];
+    let mut xy = from;
+    let mut err = delta.x - delta.y;
+    while xy != to {
+        let e2 = 2 * err;
+        if e2 > -delta.y {
+            err -= delta.y;
+            xy.x += step.x;
+        }
+        if e2 < delta.x {
+            err += delta.x;
+            xy.y += step.y;
+        }
+        cells.push(xy);
+    }
+
+    cells
+}
+
+
+/// Decrease / increase the simulation rate (speed) on key press.
 fn change_simulation_rate_on_key(
     keys: Res<'_, ButtonInput<KeyCode>>,
+    bindings: Res<'_, KeyBindings>,
     mut config: ResMut<'_, SimulationConfig>,
     mut timer: ResMut<'_, SimulationUpdateTimer>,
 ) {
     let mut tps = config.ticks_per_second;
-    if keys.just_pressed(KeyCode::Minus) {
+    if bindings.just_pressed(Binding::SpeedDown, &keys) {
         tps -= 1;
     }
-    if keys.just_pressed(KeyCode::Equal) {
+    if bindings.just_pressed(Binding::SpeedUp, &keys) {
         tps += 1;
     }
-    tps = tps.clamp(1, 64);
+    tps = tps.clamp(
+        config::sim::MIN_TICKS_PER_SECOND,
+        config::sim::MAX_TICKS_PER_SECOND,
+    );
 
-    #[allow(clippy::cast_precision_loss)]
     if tps != config.ticks_per_second {
         debug!("TPS changed: {} -> {}", config.ticks_per_second, tps);
 
-        // @REVIEW: This resets the timer.
         config.ticks_per_second = tps;
-        *timer = SimulationUpdateTimer(Timer::from_seconds(1.0 / tps as f32, TimerMode::Repeating));
+        timer.retune(tps);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::IRect;
+    use bevy::prelude::*;
+
+    use super::{cell_at, line_cells, DrawSymmetry};
+
+    #[test]
+    pub fn test_cell_at() {
+        let sprite_size = Vec2::new(16.0, 24.0);
+        // Positions are offset by half a sprite, so cells are centered on multiples of its size.
+        let offset = sprite_size / 2.0;
+
+        assert_eq!(cell_at(Vec2::ZERO - offset, sprite_size), IVec2::ZERO);
+        assert_eq!(
+            cell_at(Vec2::new(15.9, 23.9) - offset, sprite_size),
+            IVec2::ZERO
+        );
+        assert_eq!(
+            cell_at(Vec2::new(16.1, 23.9) - offset, sprite_size),
+            IVec2::new(1, 0)
+        );
+        assert_eq!(
+            cell_at(Vec2::new(15.9, 24.1) - offset, sprite_size),
+            IVec2::new(0, 1)
+        );
+        assert_eq!(
+            cell_at(Vec2::new(-0.1, -0.1) - offset, sprite_size),
+            IVec2::new(-1, -1)
+        );
+        assert_eq!(
+            cell_at(Vec2::new(40.0, 60.0) - offset, sprite_size),
+            IVec2::new(2, 2)
+        );
+    }
+
+    #[test]
+    pub fn test_line_cells() {
+        assert_eq!(line_cells(IVec2::ZERO, IVec2::ZERO), [IVec2::ZERO]);
+        assert_eq!(
+            line_cells(IVec2::new(0, 0), IVec2::new(3, 1)),
+            [(0, 0), (1, 0), (2, 1), (3, 1)].map(|(x, y)| IVec2::new(x, y))
+        );
+        assert_eq!(
+            line_cells(IVec2::new(1, 2), IVec2::new(-1, -1)),
+            [(1, 2), (0, 1), (0, 0), (-1, -1)].map(|(x, y)| IVec2::new(x, y))
+        );
+
+        // Consecutive cells are adjacent.
+        let cells = line_cells(IVec2::new(-7, 4), IVec2::new(12, -9));
+        assert!(cells
+            .windows(2)
+            .all(|pair| (pair[1] - pair[0]).abs().max_element() == 1));
+    }
+
+    #[test]
+    pub fn test_draw_symmetry() {
+        let bounds = IRect::new(-32, -18, 32, 18);
+        let xy = IVec2::new(3, -5);
+
+        assert_eq!(DrawSymmetry::None.mirror(bounds, xy), [xy]);
+        assert_eq!(
+            DrawSymmetry::Horizontal.mirror(bounds, xy),
+            [IVec2::new(-4, -5), xy]
+        );
+        assert_eq!(
+            DrawSymmetry::Vertical.mirror(bounds, xy),
+            [xy, IVec2::new(3, 4)]
+        );
+        assert_eq!(
+            DrawSymmetry::Quad.mirror(bounds, xy),
+            [(-4, -5), (-4, 4), (3, -5), (3, 4)].map(IVec2::from)
+        );
+
+        // Cells on an axis of a world of odd size are their own mirror images.
+        let bounds = IRect::new(-2, -2, 3, 3);
+        assert_eq!(
+            DrawSymmetry::Quad.mirror(bounds, IVec2::ZERO),
+            [IVec2::ZERO]
+        );
     }
 }
@@ -0,0 +1,376 @@
+//
+// Copyright (c) 2023 Martin Green. All rights reserved.
+//
+
+use std::fmt;
+
+use ahash::AHashMap as HashMap;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{ctrl_pressed, shift_pressed};
+
+
+/// Logical action triggered by a key press.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Binding {
+    Pause,
+    Advance,
+    Rewind,
+    SpeedUp,
+    SpeedDown,
+    Randomize,
+    Clear,
+    Reset,
+    Save,
+    Load,
+    ExportStats,
+    Copy,
+    Paste,
+    RotateClipboard,
+    FlipClipboardHorizontally,
+    FlipClipboardVertically,
+    DrawLine,
+    FloodFill,
+    TakeSnapshot,
+    PreviousSnapshot,
+    NextSnapshot,
+    Screenshot,
+    ZoomIn,
+    ZoomOut,
+    ResetCamera,
+    ToggleGrid,
+    ToggleHelp,
+    ToggleStats,
+}
+
+impl Binding {
+    /// All bindings, in the order they are listed in the help window.
+    pub const ALL: [Self; 28] = [
+        Self::Pause,
+        Self::Advance,
+        Self::Rewind,
+        Self::SpeedUp,
+        Self::SpeedDown,
+        Self::Randomize,
+        Self::Clear,
+        Self::Reset,
+        Self::Save,
+        Self::Load,
+        Self::ExportStats,
+        Self::Copy,
+        Self::Paste,
+        Self::RotateClipboard,
+        Self::FlipClipboardHorizontally,
+        Self::FlipClipboardVertically,
+        Self::DrawLine,
+        Self::FloodFill,
+        Self::TakeSnapshot,
+        Self::PreviousSnapshot,
+        Self::NextSnapshot,
+        Self::Screenshot,
+        Self::ZoomIn,
+        Self::ZoomOut,
+        Self::ResetCamera,
+        Self::ToggleGrid,
+        Self::ToggleHelp,
+        Self::ToggleStats,
+    ];
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Pause => "Pause / unpause the simulation.",
+            Self::Advance => "Advance the simulation a single tick (generation).",
+            Self::Rewind => "Rewind the simulation a single tick (generation).",
+            Self::SpeedUp => "Increase simulation rate (speed).",
+            Self::SpeedDown => "Decrease simulation rate (speed).",
+            Self::Randomize => "Replace the board with a random soup.",
+            Self::Clear => "Clear the board.",
+            Self::Reset => "Reset the board to the start pattern.",
+            Self::Save => "Save the simulation state.",
+            Self::Load => "Load the simulation state.",
+            Self::ExportStats => "Export the generation statistics as CSV.",
+            Self::Copy => "Copy the selection.",
+            Self::Paste => "Paste at the cursor.",
+            Self::RotateClipboard => "Rotate the clipboard 90° counterclockwise.",
+            Self::FlipClipboardHorizontally => "Flip the clipboard horizontally.",
+            Self::FlipClipboardVertically => "Flip the clipboard vertically.",
+            Self::DrawLine => "Hold to draw a line between two clicks.",
+            Self::FloodFill => "Hold to fill the clicked empty region.",
+            Self::TakeSnapshot => "Keep the board as a snapshot.",
+            Self::PreviousSnapshot => "Load the previous snapshot.",
+            Self::NextSnapshot => "Load the next snapshot.",
+            Self::Screenshot => "Save a screenshot.",
+            Self::ZoomIn => "Zoom the camera in.",
+            Self::ZoomOut => "Zoom the camera out.",
+            Self::ResetCamera => "Reset the camera.",
+            Self::ToggleGrid => "Show / hide the grid.",
+            Self::ToggleHelp => "Show / hide this help.",
+            Self::ToggleStats => "Show / hide the frame rate and tick rate.",
+        }
+    }
+}
+
+
+/// Key together with the modifiers that must be held down along with it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyChord {
+    pub key: KeyCode,
+    pub ctrl: bool,
+    pub shift: bool,
+}
+
+impl KeyChord {
+    pub const fn new(key: KeyCode) -> Self {
+        Self {
+            key,
+            ctrl: false,
+            shift: false,
+        }
+    }
+
+    pub const fn ctrl(key: KeyCode) -> Self {
+        Self {
+            key,
+            ctrl: true,
+            shift: false,
+        }
+    }
+
+    pub const fn shift(key: KeyCode) -> Self {
+        Self {
+            key,
+            ctrl: false,
+            shift: true,
+        }
+    }
+
+    /// Return `true` if the key was pressed this frame with exactly the modifiers of the chord held
+    /// down, so that e.g. `C` and `Ctrl` + `C` don't trigger each other.
+    pub fn just_pressed(&self, keys: &ButtonInput<KeyCode>) -> bool {
+        keys.just_pressed(self.key)
+            && ctrl_pressed(keys) == self.ctrl
+            && shift_pressed(keys) == self.shift
+    }
+
+    /// Return `true` if the key is held down with exactly the modifiers of the chord held down.
+    pub fn pressed(&self, keys: &ButtonInput<KeyCode>) -> bool {
+        keys.pressed(self.key)
+            && ctrl_pressed(keys) == self.ctrl
+            && shift_pressed(keys) == self.shift
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl + ")?;
+        }
+        if self.shift {
+            write!(f, "Shift + ")?;
+        }
+
+        match self.key {
+            KeyCode::Space => write!(f, "Space"),
+            KeyCode::BracketLeft => write!(f, "["),
+            KeyCode::BracketRight => write!(f, "]"),
+            KeyCode::Minus => write!(f, "-"),
+            KeyCode::Equal => write!(f, "="),
+            KeyCode::Comma => write!(f, ","),
+            KeyCode::Period => write!(f, "."),
+            KeyCode::Slash => write!(f, "/"),
+            KeyCode::Semicolon => write!(f, ";"),
+            KeyCode::Quote => write!(f, "'"),
+            KeyCode::Backquote => write!(f, "`"),
+            KeyCode::Backslash => write!(f, "\\"),
+            KeyCode::NumpadAdd => write!(f, "Num +"),
+            KeyCode::NumpadSubtract => write!(f, "Num -"),
+            key => {
+                // `KeyA` -> `A`, `Digit1` -> `1`, `F1` -> `F1`.
+                let name = format!("{key:?}");
+                let name = name
+                    .strip_prefix("Key")
+                    .or_else(|| name.strip_prefix("Digit"))
+                    .unwrap_or(&name);
+                write!(f, "{name}")
+            }
+        }
+    }
+}
+
+
+/// Binding waiting for the next key press to be bound to it.
+#[derive(Default, Resource, Deref, DerefMut)]
+pub struct PendingRebind(pub Option<Binding>);
+
+
+/// Keys bound to each logical action.
+#[derive(Resource)]
+pub struct KeyBindings(HashMap<Binding, Vec<KeyChord>>);
+
+impl KeyBindings {
+    /// Return the keys bound to `binding`.
+    pub fn get(&self, binding: Binding) -> &[KeyChord] {
+        self.0.get(&binding).map_or(&[], Vec::as_slice)
+    }
+
+    /// Return `true` if any of the keys bound to `binding` was pressed this frame.
+    pub fn just_pressed(&self, binding: Binding, keys: &ButtonInput<KeyCode>) -> bool {
+        self.get(binding)
+            .iter()
+            .any(|chord| chord.just_pressed(keys))
+    }
+
+    /// Return `true` if any of the keys bound to `binding` is held down.
+    pub fn pressed(&self, binding: Binding, keys: &ButtonInput<KeyCode>) -> bool {
+        self.get(binding).iter().any(|chord| chord.pressed(keys))
+    }
+
+    /// Bind `chord` to `binding`, replacing its previous keys. The chord is unbound from any other
+    /// binding, so a single key press never triggers two actions.
+    pub fn set(&mut self, binding: Binding, chord: KeyChord) {
+        self.set_all(binding, vec![chord]);
+    }
+
+    /// Bind `chords` to `binding`, replacing its previous keys, and unbind them from any other
+    /// binding.
+    pub fn set_all(&mut self, binding: Binding, chords: Vec<KeyChord>) {
+        for other in self.0.values_mut() {
+            other.retain(|chord| !chords.contains(chord));
+        }
+        self.0.insert(binding, chords);
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self(
+            [
+                (
+                    Binding::Pause,
+                    vec![KeyChord::new(KeyCode::Space), KeyChord::new(KeyCode::KeyP)],
+                ),
+                (Binding::Advance, vec![KeyChord::new(KeyCode::BracketRight)]),
+                (Binding::Rewind, vec![KeyChord::new(KeyCode::BracketLeft)]),
+                (
+                    Binding::SpeedUp,
+                    // `+` is typed with `Shift` on most layouts.
+                    vec![
+                        KeyChord::new(KeyCode::Equal),
+                        KeyChord::shift(KeyCode::Equal),
+                    ],
+                ),
+                (Binding::SpeedDown, vec![KeyChord::new(KeyCode::Minus)]),
+                (Binding::Randomize, vec![KeyChord::new(KeyCode::KeyR)]),
+                (Binding::Clear, vec![KeyChord::new(KeyCode::KeyC)]),
+                (Binding::Reset, vec![KeyChord::shift(KeyCode::KeyR)]),
+                (Binding::Save, vec![KeyChord::ctrl(KeyCode::KeyS)]),
+                (Binding::Load, vec![KeyChord::ctrl(KeyCode::KeyO)]),
+                (Binding::ExportStats, vec![KeyChord::ctrl(KeyCode::KeyE)]),
+                (Binding::Copy, vec![KeyChord::ctrl(KeyCode::KeyC)]),
+                (Binding::Paste, vec![KeyChord::ctrl(KeyCode::KeyV)]),
+                (Binding::RotateClipboard, vec![KeyChord::new(KeyCode::KeyQ)]),
+                (
+                    Binding::FlipClipboardHorizontally,
+                    vec![KeyChord::new(KeyCode::KeyF)],
+                ),
+                (
+                    Binding::FlipClipboardVertically,
+                    vec![KeyChord::shift(KeyCode::KeyF)],
+                ),
+                (Binding::DrawLine, vec![KeyChord::new(KeyCode::KeyL)]),
+                (Binding::FloodFill, vec![KeyChord::new(KeyCode::KeyB)]),
+                (Binding::TakeSnapshot, vec![KeyChord::new(KeyCode::KeyN)]),
+                (
+                    Binding::PreviousSnapshot,
+                    vec![KeyChord::new(KeyCode::Comma)],
+                ),
+                (Binding::NextSnapshot, vec![KeyChord::new(KeyCode::Period)]),
+                (Binding::Screenshot, vec![KeyChord::new(KeyCode::F12)]),
+                // `=` and `-` alone change the simulation rate.
+                (
+                    Binding::ZoomIn,
+                    vec![
+                        KeyChord::ctrl(KeyCode::Equal),
+                        KeyChord::new(KeyCode::NumpadAdd),
+                    ],
+                ),
+                (
+                    Binding::ZoomOut,
+                    vec![
+                        KeyChord::ctrl(KeyCode::Minus),
+                        KeyChord::new(KeyCode::NumpadSubtract),
+                    ],
+                ),
+                (Binding::ResetCamera, vec![KeyChord::new(KeyCode::Home)]),
+                (Binding::ToggleGrid, vec![KeyChord::new(KeyCode::KeyG)]),
+                (Binding::ToggleHelp, vec![KeyChord::new(KeyCode::F1)]),
+                (Binding::ToggleStats, vec![KeyChord::new(KeyCode::F3)]),
+            ]
+            .into_iter()
+            .collect(),
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use super::{Binding, KeyBindings, KeyChord};
+
+    #[test]
+    pub fn test_key_chord_display() {
+        assert_eq!(KeyChord::new(KeyCode::KeyP).to_string(), "P");
+        assert_eq!(KeyChord::new(KeyCode::BracketLeft).to_string(), "[");
+        assert_eq!(KeyChord::ctrl(KeyCode::KeyS).to_string(), "Ctrl + S");
+        assert_eq!(KeyChord::shift(KeyCode::Digit1).to_string(), "Shift + 1");
+        assert_eq!(KeyChord::new(KeyCode::F12).to_string(), "F12");
+        assert_eq!(KeyChord::new(KeyCode::NumpadAdd).to_string(), "Num +");
+    }
+
+    #[test]
+    pub fn test_default_bindings() {
+        let bindings = KeyBindings::default();
+        for binding in Binding::ALL {
+            assert!(!bindings.get(binding).is_empty(), "{binding:?} is unbound");
+        }
+
+        let mut keys = ButtonInput::<KeyCode>::default();
+        keys.press(KeyCode::KeyC);
+        assert!(bindings.just_pressed(Binding::Clear, &keys));
+        assert!(!bindings.just_pressed(Binding::Copy, &keys));
+
+        keys.press(KeyCode::ControlLeft);
+        assert!(!bindings.just_pressed(Binding::Clear, &keys));
+        assert!(bindings.just_pressed(Binding::Copy, &keys));
+
+        // Zooming with `Ctrl` doesn't change the simulation rate.
+        keys.press(KeyCode::Equal);
+        assert!(bindings.just_pressed(Binding::ZoomIn, &keys));
+        assert!(!bindings.just_pressed(Binding::SpeedUp, &keys));
+
+        keys.release(KeyCode::ControlLeft);
+        keys.press(KeyCode::ShiftLeft);
+        assert!(bindings.just_pressed(Binding::SpeedUp, &keys));
+    }
+
+    #[test]
+    pub fn test_set_binding() {
+        let mut bindings = KeyBindings::default();
+
+        bindings.set(Binding::Advance, KeyChord::new(KeyCode::KeyP));
+        assert_eq!(
+            bindings.get(Binding::Advance),
+            [KeyChord::new(KeyCode::KeyP)]
+        );
+        assert_eq!(
+            bindings.get(Binding::Pause),
+            [KeyChord::new(KeyCode::Space)]
+        );
+
+        bindings.set(Binding::Clear, KeyChord::ctrl(KeyCode::KeyC));
+        assert!(bindings.get(Binding::Copy).is_empty());
+    }
+}
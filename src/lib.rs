@@ -0,0 +1,699 @@
+//
+// Copyright (c) 2023 Martin Green. All rights reserved.
+//
+
+use bevy::asset::AssetMetaCheck;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::time::common_conditions::on_timer;
+use bevy::window::PrimaryWindow;
+use game::Life;
+use serde::{Deserialize, Serialize};
+
+use crate::assets::GlyphAtlas;
+use crate::color_gradient::{to_u8, ColorGradient};
+
+
+mod assets;
+mod camera;
+mod cli;
+mod color_gradient;
+mod config;
+mod editor;
+pub mod game;
+mod input;
+mod patterns;
+mod settings;
+mod ui;
+
+
+#[derive(Default, Resource)]
+struct WindowFocus {
+    focused: bool,
+}
+
+#[derive(Event)]
+struct WindowFocused {
+    focused: bool,
+}
+
+
+/// Whether to draw grid lines between cells.
+#[derive(Default, Resource, Deref, DerefMut)]
+struct ShowGrid(bool);
+
+
+/// How the board is drawn.
+#[derive(Default, Resource, Clone, Copy, Debug, PartialEq, Eq)]
+enum RenderMode {
+    /// A fontsheet glyph sprite per cell.
+    #[default]
+    Glyphs,
+    /// A single texture with a pixel per cell. Plainer, but much cheaper for large boards.
+    Texture,
+}
+
+
+/// Glyph live cells are drawn with in `RenderMode::Glyphs`.
+#[derive(Default, Resource, Clone, Copy, Debug, PartialEq, Eq)]
+enum GlyphMode {
+    /// A solid block.
+    #[default]
+    Block,
+    /// The digit of their age, capped at 9.
+    AgeDigit,
+}
+
+impl GlyphMode {
+    /// Return the fontsheet glyph of a live cell of `age`.
+    fn glyph(self, age: u32) -> usize {
+        match self {
+            Self::Block => config::cells::ALIVE_GLYPH,
+            Self::AgeDigit => config::cells::DIGIT_GLYPH + age.min(9) as usize,
+        }
+    }
+}
+
+
+/// Sprite drawing the whole board in `RenderMode::Texture`.
+#[derive(Component)]
+struct BoardTexture;
+
+
+/// Gradient live cells are colored by according to their age, relative to the oldest live cell.
+#[derive(Resource)]
+struct AgeGradient {
+    gradient: ColorGradient,
+    /// Samples of `gradient`, see `config::cells::AGE_GRADIENT_STEPS`.
+    baked: Vec<Srgba>,
+}
+
+impl AgeGradient {
+    fn new(gradient: ColorGradient) -> Self {
+        let baked = gradient.bake(config::cells::AGE_GRADIENT_STEPS);
+        Self { gradient, baked }
+    }
+
+    fn gradient(&self) -> &ColorGradient {
+        &self.gradient
+    }
+
+    /// Replace the gradient.
+    fn set(&mut self, gradient: ColorGradient) {
+        *self = Self::new(gradient);
+    }
+
+    /// Sample the gradient at `q` in [0.0, 1.0] range.
+    fn sample(&self, q: f32) -> Srgba {
+        ColorGradient::sample_baked(&self.baked, q)
+    }
+}
+
+impl Default for AgeGradient {
+    fn default() -> Self {
+        Self::new(config::cells::age_gradient())
+    }
+}
+
+
+/// What live cells are colored by.
+#[derive(Default, Resource, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum ColorMode {
+    /// Their age, see [`AgeColoring`].
+    #[default]
+    Age,
+    /// Their number of live neighbors, see `config::cells::NEIGHBOR_COUNT_COLORS`.
+    NeighborCount,
+}
+
+
+/// Whether live cells are colored by their age. Otherwise, all live cells are colored alike, with
+/// [`LiveColor`].
+#[derive(Resource, Deref, DerefMut)]
+struct AgeColoring(bool);
+
+impl Default for AgeColoring {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+
+/// Color of all live cells without [`AgeColoring`].
+#[derive(Resource, Deref, DerefMut)]
+struct LiveColor(Srgba);
+
+impl Default for LiveColor {
+    fn default() -> Self {
+        Self(config::cells::LIVE_COLOR)
+    }
+}
+
+
+/// Color of dead cells, and the color dying cells fade to.
+#[derive(Resource, Deref, DerefMut)]
+struct DeadColor(Srgba);
+
+impl Default for DeadColor {
+    fn default() -> Self {
+        Self(config::cells::DEAD_COLOR)
+    }
+}
+
+
+/// Everything cells are colored by.
+#[derive(SystemParam)]
+struct CellPalette<'w> {
+    sim_config: Res<'w, game::SimulationConfig>,
+    gradient: Res<'w, AgeGradient>,
+    color_mode: Res<'w, ColorMode>,
+    age_coloring: Res<'w, AgeColoring>,
+    live_color: Res<'w, LiveColor>,
+    dead_color: Res<'w, DeadColor>,
+}
+
+impl CellPalette<'_> {
+    /// Return the color of the cell at `xy`, or `None` if there is no live or dying cell there.
+    fn color(&self, life: &Life, xy: IVec2) -> Option<Srgba> {
+        use config::cells::{DYING_COLOR, IMMIGRATION_COLORS, NEIGHBOR_COUNT_COLORS};
+
+        let cell = life.cells.get(&xy)?;
+        let color = if !cell.alive {
+            // Fade from the dying color to the dead color as the cell decays.
+            let decay_states = self.sim_config.rule.decay_states.max(1);
+            let q = f32::from(cell.state) / f32::from(decay_states);
+            self.dead_color.mix(&DYING_COLOR, q)
+        } else if *self.color_mode == ColorMode::NeighborCount {
+            let count = life.live_neighbors(&self.sim_config, xy);
+            NEIGHBOR_COUNT_COLORS[count.min(NEIGHBOR_COUNT_COLORS.len() - 1)]
+        } else if self.sim_config.variant == game::RuleVariant::Immigration {
+            // Tint by color rather than age.
+            IMMIGRATION_COLORS[cell.color as usize]
+        } else if !**self.age_coloring {
+            **self.live_color
+        } else {
+            // REVIEW:
+            //   There should be a better way to handle this. Fortunately, any bugs will only
+            //   manifest when cell age is greater than 2^24 (16,777,216).
+            #[allow(clippy::cast_precision_loss)]
+            let q = (cell.age as f32) / (life.max_age.max(1) as f32);
+            self.gradient.sample(q)
+        };
+
+        Some(color)
+    }
+
+    /// Return `true` if any of the colors changed since the system last ran.
+    fn is_changed(&self) -> bool {
+        self.sim_config.is_changed()
+            || self.gradient.is_changed()
+            || self.color_mode.is_changed()
+            || self.age_coloring.is_changed()
+            || self.live_color.is_changed()
+            || self.dead_color.is_changed()
+    }
+}
+
+
+/// Run the game until its window is closed.
+pub fn run() {
+    // @REVIEW: See <https://github.com/bevy-cheatbook/bevy-cheatbook/issues/196>.
+    #[cfg(target_arch = "wasm32")]
+    console_error_panic_hook::set_once();
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut warnings = config::load_overrides(std::path::Path::new(config::OVERRIDES_PATH));
+    #[cfg(target_arch = "wasm32")]
+    let mut warnings: Vec<String> = Vec::new();
+
+    // Saved settings override the defaults and `config.toml`, and options override both.
+    let settings = settings::Settings::load(&mut warnings);
+    let args = cli::Args::from_env(&mut warnings);
+    // Patterns that can't be loaded fall back to the default pattern once logging is set up.
+    let mut game = game::GamePlugin::default();
+    if let Some(rule) = settings.rule {
+        game.rule = rule;
+    }
+    if let Some(tps) = settings.ticks_per_second {
+        game.ticks_per_second = tps;
+    }
+    if let Some(path) = args.pattern {
+        game.initial_pattern = game::InitialPattern::File(path);
+    }
+    if let Some(rule) = args.rule {
+        game.rule = rule;
+    }
+    if let Some(tps) = args.ticks_per_second {
+        game.ticks_per_second = tps;
+    }
+
+    let width = config::window::width();
+    let height = config::window::height();
+    let sprite_size = config::cells::sprite_size();
+
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    let (columns, rows) = (
+        (width as f32 / sprite_size.x) as u32,
+        (height as f32 / sprite_size.y) as u32,
+    );
+    let (columns, rows) = settings
+        .board_size
+        .map_or((columns, rows), |size| (size.x, size.y));
+
+    #[allow(clippy::cast_precision_loss)]
+    App::new()
+        .insert_resource(Msaa::Off)
+        .insert_resource(ClearColor(Color::BLACK))
+        .insert_resource(Life::new(columns, rows))
+        .add_event::<WindowFocused>()
+        .init_resource::<ShowGrid>()
+        .init_resource::<RenderMode>()
+        .init_resource::<GlyphMode>()
+        .insert_resource(settings.age_gradient().unwrap_or_default())
+        .insert_resource(settings.color_mode.unwrap_or_default())
+        .insert_resource(settings.key_bindings())
+        .init_resource::<AgeColoring>()
+        .init_resource::<LiveColor>()
+        .init_resource::<DeadColor>()
+        .add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: Some(Window {
+                        resolution: (width as f32, height as f32).into(),
+                        position: WindowPosition::Centered(MonitorSelection::Primary),
+                        title: String::from(config::window::TITLE),
+                        prevent_default_event_handling: false,
+                        ..default()
+                    }),
+                    ..default()
+                })
+                .set(AssetPlugin {
+                    meta_check: AssetMetaCheck::Never,
+                    ..default()
+                })
+                .set(ImagePlugin::default_nearest()),
+        )
+        .init_state::<AppState>()
+        .add_plugins(input::InputPlugin)
+        .add_plugins(ui::UiPlugin)
+        .add_plugins(camera::CameraPlugin)
+        .add_plugins(assets::AssetPlugin)
+        .add_plugins(game)
+        .add_plugins(editor::EditorPlugin)
+        .add_plugins(settings::SettingsPlugin)
+        .add_systems(
+            Startup,
+            |mut next_state: ResMut<'_, NextState<AppState>>| next_state.set(AppState::Startup),
+        )
+        .add_systems(Startup, move || {
+            for warning in &warnings {
+                warn!("{warning}");
+            }
+        })
+        .add_systems(PreUpdate, track_window_focus)
+        .add_systems(
+            Update,
+            update_window_title.run_if(on_timer(config::window::TITLE_UPDATE_INTERVAL)),
+        )
+        .add_systems(
+            OnEnter(AppState::Running),
+            init_presentation.run_if(run_once()),
+        )
+        .add_systems(
+            Update,
+            (
+                (
+                    respawn_presentation
+                        .run_if(on_event::<game::GridChanged>().or_else(render_mode_changed)),
+                    update_presentation,
+                    update_board_texture,
+                )
+                    .chain()
+                    .after(game::GameLogicSet),
+                toggle_grid.run_if(on_event::<input::InputAction>()),
+                draw_grid.run_if(|show: Res<'_, ShowGrid>| **show),
+            ),
+        )
+        .run();
+}
+
+
+/// The single application state, gating asset loading and the simulation systems in all modules.
+#[derive(States, Clone, Debug, Default, PartialEq, Eq, Hash)]
+enum AppState {
+    #[default]
+    None,
+    /// Loading assets.
+    Startup,
+    /// The simulation advances on every tick of the `SimulationUpdateTimer`.
+    Running,
+    /// The simulation only advances or rewinds on request.
+    Paused,
+    /// Loading assets failed, see `assets::AssetLoadFailure`.
+    Error,
+}
+
+
+#[derive(Component, Deref)]
+struct Position(pub IVec2);
+
+
+/// Fade of a cell sprite from its last color to the dead color, once the cell dies.
+#[derive(Component, Default)]
+struct FadeOut {
+    /// Color the cell was last drawn with while alive or dying.
+    from: Option<Srgba>,
+    /// Time the cell was first drawn dead, in seconds since startup.
+    started: Option<f32>,
+}
+
+
+fn init_presentation(
+    mut commands: Commands<'_, '_>,
+    world: Res<'_, Life>,
+    glyphs: Res<'_, GlyphAtlas>,
+    palette: CellPalette<'_>,
+    mode: Res<'_, RenderMode>,
+    mut images: ResMut<'_, Assets<Image>>,
+) {
+    match *mode {
+        RenderMode::Glyphs => spawn_presentation(&mut commands, &world, &glyphs, &palette),
+        RenderMode::Texture => spawn_board_texture(&mut commands, &world, &mut images),
+    }
+}
+
+
+/// Whether the render mode was switched, as opposed to just initialized.
+fn render_mode_changed(mode: Res<'_, RenderMode>) -> bool {
+    mode.is_changed() && !mode.is_added()
+}
+
+
+/// Despawn the presentation and spawn it anew for the current world bounds and render mode.
+#[allow(clippy::too_many_arguments)]
+fn respawn_presentation(
+    mut commands: Commands<'_, '_>,
+    world: Res<'_, Life>,
+    glyphs: Res<'_, GlyphAtlas>,
+    palette: CellPalette<'_>,
+    mode: Res<'_, RenderMode>,
+    mut images: ResMut<'_, Assets<Image>>,
+    q_sprites: Query<'_, '_, Entity, Or<(With<Position>, With<BoardTexture>)>>,
+    mut ev_grid_changed: EventReader<'_, '_, game::GridChanged>,
+) {
+    // Rebuild once, however many times the grid changed this frame.
+    ev_grid_changed.clear();
+
+    for entity in &q_sprites {
+        commands.entity(entity).despawn();
+    }
+    match *mode {
+        RenderMode::Glyphs => spawn_presentation(&mut commands, &world, &glyphs, &palette),
+        RenderMode::Texture => spawn_board_texture(&mut commands, &world, &mut images),
+    }
+}
+
+
+/// Spawn a single sprite covering the world bounds, with a texture of a pixel per cell. The pixels
+/// are filled in by `update_board_texture`.
+fn spawn_board_texture(commands: &mut Commands<'_, '_>, world: &Life, images: &mut Assets<Image>) {
+    let size = world.bounds.size().max(IVec2::ONE).as_uvec2();
+    let image = Image::new_fill(
+        Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 0],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+
+    let sprite_size = config::cells::sprite_size();
+    let center = (world.bounds.min + world.bounds.max).as_vec2() / 2.0 * sprite_size;
+    commands.spawn((
+        SpriteBundle {
+            texture: images.add(image),
+            sprite: Sprite {
+                custom_size: Some(size.as_vec2() * sprite_size),
+                ..default()
+            },
+            transform: Transform::from_translation(center.extend(0.0)),
+            ..default()
+        },
+        BoardTexture,
+    ));
+}
+
+
+fn spawn_presentation(
+    commands: &mut Commands<'_, '_>,
+    world: &Life,
+    glyphs: &GlyphAtlas,
+    palette: &CellPalette<'_>,
+) {
+    let sprite_size = config::cells::sprite_size();
+
+    for y in world.bounds.min.y..world.bounds.max.y {
+        for x in world.bounds.min.x..world.bounds.max.x {
+            let (atlas, sprite) = if let Some(color) = palette.color(world, IVec2::new(x, y)) {
+                (
+                    TextureAtlas {
+                        layout: glyphs.0.clone(),
+                        index: config::cells::ALIVE_GLYPH,
+                    },
+                    Sprite {
+                        color: color.into(),
+                        custom_size: Some(sprite_size),
+                        ..default()
+                    },
+                )
+            } else {
+                (
+                    TextureAtlas {
+                        layout: glyphs.0.clone(),
+                        index: config::cells::DEAD_GLYPH,
+                    },
+                    Sprite {
+                        color: palette.dead_color.0.into(),
+                        custom_size: Some(sprite_size),
+                        ..default()
+                    },
+                )
+            };
+
+            #[allow(clippy::cast_precision_loss)]
+            let transform = Transform::from_translation(
+                (Vec2::new(x as f32, y as f32) * sprite_size
+                    + config::cells::sprite_world_offset())
+                .extend(0.0),
+            );
+            commands.spawn((
+                SpriteBundle {
+                    texture: glyphs.1.clone(),
+                    sprite,
+                    transform,
+                    ..default()
+                },
+                atlas,
+                Position(IVec2::new(x, y)),
+                FadeOut::default(),
+            ));
+        }
+    }
+}
+
+
+/// Return the cells within the view of the main camera, if any, with both corners inclusive.
+fn visible_cells(
+    q_camera: &Query<'_, '_, (&Camera, &GlobalTransform), With<camera::MainCamera>>,
+) -> Option<IRect> {
+    let (camera, global_transform) = q_camera.get_single().ok()?;
+    let viewport = camera.logical_viewport_rect()?;
+
+    // The viewport is y-down, so its corners map to opposite world corners.
+    let a = camera.viewport_to_world_2d(global_transform, viewport.min)?;
+    let b = camera.viewport_to_world_2d(global_transform, viewport.max)?;
+
+    let sprite_size = config::cells::sprite_size();
+    let min = (a.min(b) / sprite_size).floor().as_ivec2();
+    let max = (a.max(b) / sprite_size).floor().as_ivec2();
+
+    Some(IRect::from_corners(min, max))
+}
+
+
+/// Update the presentation. Only the sprites within the view of the main camera are updated,
+/// others are updated once they come into view.
+fn update_presentation(
+    time: Res<'_, Time>,
+    life: Res<'_, Life>,
+    palette: CellPalette<'_>,
+    glyph_mode: Res<'_, GlyphMode>,
+    q_camera: Query<'_, '_, (&Camera, &GlobalTransform), With<camera::MainCamera>>,
+    mut q_sprites: Query<'_, '_, (&Position, &mut FadeOut, &mut TextureAtlas, &mut Sprite)>,
+) {
+    let visible = visible_cells(&q_camera);
+    let now = time.elapsed_seconds();
+
+    for (position, mut fade, mut atlas, mut sprite) in &mut q_sprites {
+        if visible.is_some_and(|visible| !visible.contains(**position)) {
+            // Cells that die out of view have long faded by the time they come into view.
+            if fade.from.is_some() {
+                *fade = FadeOut::default();
+            }
+            continue;
+        }
+
+        if let Some(color) = palette.color(&life, **position) {
+            // Dying cells have no age, so they are always drawn as blocks.
+            atlas.index = life
+                .age_of(**position)
+                .map_or(config::cells::ALIVE_GLYPH, |age| glyph_mode.glyph(age));
+            sprite.color = color.into();
+
+            // Coming back to life, e.g. by rewinding, cancels the fade.
+            fade.from = Some(color);
+            fade.started = None;
+            continue;
+        }
+
+        let dead_color = palette.dead_color.0;
+        if let Some(from) = fade.from {
+            let started = *fade.started.get_or_insert(now);
+            let t = (now - started) / config::cells::FADE_OUT_DURATION.as_secs_f32();
+            if t < 1.0 {
+                atlas.index = config::cells::ALIVE_GLYPH;
+                sprite.color = from.mix(&dead_color, t).into();
+                continue;
+            }
+            *fade = FadeOut::default();
+        }
+
+        atlas.index = config::cells::DEAD_GLYPH;
+        sprite.color = dead_color.into();
+    }
+}
+
+
+/// Write the cell colors into the board texture whenever the board or its colors change.
+fn update_board_texture(
+    life: Res<'_, Life>,
+    palette: CellPalette<'_>,
+    q_texture: Query<'_, '_, (&Handle<Image>, Ref<'_, BoardTexture>)>,
+    mut images: ResMut<'_, Assets<Image>>,
+) {
+    let Ok((handle, board)) = q_texture.get_single() else {
+        return;
+    };
+    if !board.is_added() && !life.is_changed() && !palette.is_changed() {
+        return;
+    }
+    let Some(image) = images.get_mut(handle) else {
+        return;
+    };
+
+    let bounds = life.bounds;
+    #[allow(clippy::cast_sign_loss)]
+    let width = bounds.width() as usize;
+    for y in bounds.min.y..bounds.max.y {
+        for x in bounds.min.x..bounds.max.x {
+            // Image rows go top to bottom, while world rows go bottom to top.
+            #[allow(clippy::cast_sign_loss)]
+            let (column, row) = ((x - bounds.min.x) as usize, (bounds.max.y - 1 - y) as usize);
+            let index = (row * width + column) * 4;
+
+            let color = palette
+                .color(&life, IVec2::new(x, y))
+                .unwrap_or(palette.dead_color.0);
+            image.data[index..index + 4].copy_from_slice(&[
+                to_u8(color.red),
+                to_u8(color.green),
+                to_u8(color.blue),
+                to_u8(color.alpha),
+            ]);
+        }
+    }
+}
+
+
+fn toggle_grid(
+    mut show: ResMut<'_, ShowGrid>,
+    mut actions: EventReader<'_, '_, input::InputAction>,
+) {
+    for action in actions.read() {
+        if let input::InputAction::ToggleGrid = action {
+            **show = !**show;
+        }
+    }
+}
+
+
+/// Draw grid lines along the cell edges.
+fn draw_grid(life: Res<'_, Life>, mut gizmos: Gizmos<'_, '_>) {
+    use config::cells::GRID_COLOR;
+
+    let sprite_size = config::cells::sprite_size();
+
+    #[allow(clippy::cast_precision_loss)]
+    let min = Vec2::new(life.bounds.min.x as f32, life.bounds.min.y as f32) * sprite_size;
+    #[allow(clippy::cast_precision_loss)]
+    let max = Vec2::new(life.bounds.max.x as f32, life.bounds.max.y as f32) * sprite_size;
+
+    for x in life.bounds.min.x..=life.bounds.max.x {
+        #[allow(clippy::cast_precision_loss)]
+        let x = x as f32 * sprite_size.x;
+        gizmos.line_2d(Vec2::new(x, min.y), Vec2::new(x, max.y), GRID_COLOR);
+    }
+    for y in life.bounds.min.y..=life.bounds.max.y {
+        #[allow(clippy::cast_precision_loss)]
+        let y = y as f32 * sprite_size.y;
+        gizmos.line_2d(Vec2::new(min.x, y), Vec2::new(max.x, y), GRID_COLOR);
+    }
+}
+
+
+/// Show the generation and population in the window title.
+fn update_window_title(
+    life: Res<'_, Life>,
+    mut q_window: Query<'_, '_, &mut Window, With<PrimaryWindow>>,
+) {
+    let Ok(mut window) = q_window.get_single_mut() else {
+        return;
+    };
+
+    let population = life.population();
+    window.title = format!(
+        "{} — gen {}, pop {population}",
+        config::window::TITLE,
+        life.generation
+    );
+}
+
+
+fn track_window_focus(
+    mut focus: Local<'_, WindowFocus>,
+    mut ev_focused_bevy: EventReader<'_, '_, bevy::window::WindowFocused>,
+    mut ev_focused: EventWriter<'_, WindowFocused>,
+) {
+    let focused = focus.focused;
+
+    // Aggregate focus events.
+    for event in ev_focused_bevy.read() {
+        debug!("{event:?}");
+        focus.focused = event.focused;
+    }
+
+    if focus.focused != focused {
+        ev_focused.send(WindowFocused {
+            focused: focus.focused,
+        });
+    }
+}
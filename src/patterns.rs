@@ -0,0 +1,368 @@
+//
+// Copyright (c) 2023 Martin Green. All rights reserved.
+//
+
+use std::fmt;
+
+use bevy::prelude::*;
+
+use crate::game::Cell;
+
+
+/// "Butterfly" pattern.
+pub const BUTTERFLY: &str = "x = 3, y = 7, rule = B3/S23\nbo$bo$bo$3o$bo$bo$bo!";
+
+
+/// Built-in patterns by name.
+// `format_strings` splits long literals, even within escapes, so keep rustfmt away from them.
+#[rustfmt::skip]
+pub const LIBRARY: [(&str, &str); 5] = [
+    ("Glider", "x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!"),
+    ("LWSS", "x = 5, y = 4, rule = B3/S23\nbo2bo$o4b$o3bo$4o!"),
+    (
+        "Pulsar",
+        "x = 13, y = 13, rule = B3/S23\n\
+         2b3o3b3o2$o4bobo4bo$o4bobo4bo$o4bobo4bo$2b3o3b3o2$2b3o3b3o$o4bobo4bo$o4bobo4bo$\
+         o4bobo4bo2$2b3o3b3o!",
+    ),
+    (
+        "Gosper glider gun",
+        "x = 36, y = 9, rule = B3/S23\n\
+         24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4bobo$\
+         10bo5bo7bo$11bo3bo$12b2o!",
+    ),
+    ("R-pentomino", "x = 3, y = 3, rule = B3/S23\nb2o$2o$bo!"),
+];
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RleError {
+    /// The input has no `x = N, y = M` header line.
+    MissingHeader,
+    /// The header line is malformed.
+    InvalidHeader(String),
+    /// A run count is too large.
+    InvalidRunCount { line: usize },
+    /// The body contains a character that isn't a run count or a `b`/`o`/`$`/`!` token.
+    UnexpectedCharacter { line: usize, character: char },
+    /// A cell lies outside of the size declared by the header.
+    OutOfBounds { line: usize, x: i32, y: i32 },
+    /// The body isn't terminated by `!`.
+    MissingTerminator,
+}
+
+impl fmt::Display for RleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingHeader => write!(f, "missing `x = .., y = ..` header"),
+            Self::InvalidHeader(header) => write!(f, "invalid header `{header}`"),
+            Self::InvalidRunCount { line } => write!(f, "line {line}: invalid run count"),
+            Self::UnexpectedCharacter { line, character } => {
+                write!(f, "line {line}: unexpected character `{character}`")
+            }
+            Self::OutOfBounds { line, x, y } => {
+                write!(
+                    f,
+                    "line {line}: cell ({x}, {y}) lies outside of the declared size"
+                )
+            }
+            Self::MissingTerminator => write!(f, "pattern isn't terminated by `!`"),
+        }
+    }
+}
+
+impl std::error::Error for RleError {}
+
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PatternError {
+    /// The pattern isn't valid RLE.
+    Rle(RleError),
+    /// The body of a plaintext pattern contains a character other than `.` or `O`.
+    UnexpectedCharacter { line: usize, character: char },
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rle(err) => err.fmt(f),
+            Self::UnexpectedCharacter { line, character } => {
+                write!(f, "line {line}: unexpected character `{character}`")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PatternError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Rle(err) => Some(err),
+            Self::UnexpectedCharacter { .. } => None,
+        }
+    }
+}
+
+impl From<RleError> for PatternError {
+    fn from(err: RleError) -> Self {
+        Self::Rle(err)
+    }
+}
+
+
+/// Parse a pattern in the [RLE](https://conwaylife.com/wiki/Run_Length_Encoded) format.
+///
+/// The returned cells are centered on the origin. RLE rows run top to bottom, so they are flipped
+/// to match the y-up convention of the world.
+///
+/// # Errors
+///
+/// Returns an error if the header or the body of the pattern is malformed.
+pub fn parse_rle(input: &str) -> Result<Vec<(IVec2, Cell)>, RleError> {
+    let mut lines = input
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+    let Some((_, header)) = lines.next() else {
+        return Err(RleError::MissingHeader);
+    };
+    let (width, height) = parse_rle_header(header)?;
+
+    let mut cells = Vec::new();
+    let mut run: Option<i32> = None;
+    let mut col = 0;
+    let mut row = 0;
+
+    for (line, body) in lines {
+        for character in body.chars() {
+            if let Some(digit) = character.to_digit(10) {
+                #[allow(clippy::cast_possible_wrap)]
+                let digit = digit as i32;
+                run = run
+                    .unwrap_or(0)
+                    .checked_mul(10)
+                    .and_then(|n| n.checked_add(digit))
+                    .map(Some)
+                    .ok_or(RleError::InvalidRunCount { line })?;
+                continue;
+            }
+
+            let count = run.take().unwrap_or(1);
+            match character {
+                'b' => {
+                    col = col
+                        .checked_add(count)
+                        .ok_or(RleError::InvalidRunCount { line })?;
+                }
+                'o' => {
+                    for _ in 0..count {
+                        if col >= width || row >= height {
+                            return Err(RleError::OutOfBounds {
+                                line,
+                                x: col,
+                                y: row,
+                            });
+                        }
+                        cells.push((
+                            IVec2::new(col - width / 2, (height - 1) / 2 - row),
+                            Cell::default(),
+                        ));
+                        col += 1;
+                    }
+                }
+                '$' => {
+                    row = row
+                        .checked_add(count)
+                        .ok_or(RleError::InvalidRunCount { line })?;
+                    col = 0;
+                }
+                '!' => return Ok(cells),
+                _ if character.is_whitespace() => {}
+                _ => return Err(RleError::UnexpectedCharacter { line, character }),
+            }
+        }
+    }
+
+    Err(RleError::MissingTerminator)
+}
+
+
+/// Parse a pattern in the [plaintext](https://conwaylife.com/wiki/Plaintext) (`.cells`) format.
+///
+/// Lines starting with `!` are comments. The returned cells are centered on the origin. Rows run
+/// top to bottom, so they are flipped to match the y-up convention of the world.
+///
+/// # Errors
+///
+/// Returns an error if the body of the pattern contains a character other than `.` or `O`.
+pub fn parse_plaintext(input: &str) -> Result<Vec<IVec2>, PatternError> {
+    let mut rows = input
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim_end()))
+        .filter(|(_, line)| !line.starts_with('!'))
+        .collect::<Vec<_>>();
+
+    // Trailing blank lines aren't part of the pattern.
+    while rows.last().is_some_and(|(_, row)| row.is_empty()) {
+        rows.pop();
+    }
+
+    let mut alive = Vec::new();
+    for (row, (line, body)) in rows.iter().enumerate() {
+        for (col, character) in body.chars().enumerate() {
+            match character {
+                '.' => {}
+                'O' => alive.push((col, row)),
+                _ => {
+                    return Err(PatternError::UnexpectedCharacter {
+                        line: *line,
+                        character,
+                    });
+                }
+            }
+        }
+    }
+
+    let width = rows
+        .iter()
+        .map(|(_, row)| row.chars().count())
+        .max()
+        .unwrap_or(0);
+    let height = rows.len();
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    let cells = alive
+        .into_iter()
+        .map(|(col, row)| {
+            IVec2::new(
+                col as i32 - (width / 2) as i32,
+                (height.saturating_sub(1) / 2) as i32 - row as i32,
+            )
+        })
+        .collect();
+
+    Ok(cells)
+}
+
+
+/// Parse an RLE header line of the form `x = N, y = M, rule = B3/S23`, returning the declared
+/// width and height. The rule is optional.
+fn parse_rle_header(header: &str) -> Result<(i32, i32), RleError> {
+    let invalid = || RleError::InvalidHeader(header.to_owned());
+
+    let mut width = None;
+    let mut height = None;
+    for field in header.split(',') {
+        let (key, value) = field.split_once('=').ok_or_else(invalid)?;
+        let value = value.trim();
+        match key.trim() {
+            "x" => width = Some(value.parse::<u16>().map_err(|_| invalid())?),
+            "y" => height = Some(value.parse::<u16>().map_err(|_| invalid())?),
+            "rule" => {}
+            _ => return Err(invalid()),
+        }
+    }
+
+    match (width, height) {
+        (Some(width), Some(height)) => Ok((i32::from(width), i32::from(height))),
+        _ => Err(invalid()),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use super::{parse_plaintext, parse_rle, PatternError, RleError, BUTTERFLY, LIBRARY};
+
+    #[test]
+    pub fn test_parse_rle() {
+        let mut cells = parse_rle(BUTTERFLY)
+            .unwrap()
+            .into_iter()
+            .map(|(xy, _)| xy)
+            .collect::<Vec<_>>();
+        cells.sort_by_key(|xy| (xy.x, xy.y));
+
+        let mut expected = (-3..=3).map(|y| IVec2::new(0, y)).collect::<Vec<_>>();
+        expected.extend([IVec2::new(-1, 0), IVec2::new(1, 0)]);
+        expected.sort_by_key(|xy| (xy.x, xy.y));
+
+        assert_eq!(cells, expected);
+    }
+
+    #[test]
+    pub fn test_library() {
+        let populations = LIBRARY.map(|(name, rle)| {
+            let cells = parse_rle(rle).unwrap_or_else(|err| panic!("{name}: {err}"));
+            (name, cells.len())
+        });
+
+        assert_eq!(
+            populations,
+            [
+                ("Glider", 5),
+                ("LWSS", 9),
+                ("Pulsar", 48),
+                ("Gosper glider gun", 36),
+                ("R-pentomino", 5),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_parse_plaintext() {
+        let cells = parse_plaintext("!Name: Glider\n!\n.O.\n..O\nOOO\n").unwrap();
+        assert_eq!(
+            cells,
+            [(0, 1), (1, 0), (-1, -1), (0, -1), (1, -1)].map(|(x, y)| IVec2::new(x, y))
+        );
+
+        assert_eq!(
+            parse_plaintext(".O.\n.X."),
+            Err(PatternError::UnexpectedCharacter {
+                line: 2,
+                character: 'X'
+            })
+        );
+    }
+
+    #[test]
+    pub fn test_parse_rle_errors() {
+        assert_eq!(parse_rle(""), Err(RleError::MissingHeader));
+        assert_eq!(
+            parse_rle("x = 3"),
+            Err(RleError::InvalidHeader(String::from("x = 3")))
+        );
+        assert_eq!(
+            parse_rle("x = 3, y = 1\n3o"),
+            Err(RleError::MissingTerminator)
+        );
+        assert_eq!(
+            parse_rle("x = 3, y = 1\n3q!"),
+            Err(RleError::UnexpectedCharacter {
+                line: 2,
+                character: 'q'
+            })
+        );
+        assert_eq!(
+            parse_rle("x = 2, y = 1\n3o!"),
+            Err(RleError::OutOfBounds {
+                line: 2,
+                x: 2,
+                y: 0
+            })
+        );
+        assert_eq!(
+            parse_rle("x = 3, y = 3\n2147483647b2147483647b!"),
+            Err(RleError::InvalidRunCount { line: 2 })
+        );
+        assert_eq!(
+            parse_rle("x = 3, y = 3\n2147483647$2147483647$!"),
+            Err(RleError::InvalidRunCount { line: 2 })
+        );
+    }
+}
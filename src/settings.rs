@@ -0,0 +1,276 @@
+//
+// Copyright (c) 2023 Martin Green. All rights reserved.
+//
+
+use std::io;
+
+use bevy::app::AppExit;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::time::common_conditions::on_timer;
+use serde::{Deserialize, Serialize};
+
+use crate::color_gradient::ColorGradient;
+use crate::game::{Life, Rule, SimulationConfig};
+use crate::input::bindings::{Binding, KeyBindings, KeyChord};
+use crate::{config, AgeGradient, ColorMode};
+
+
+/// Settings kept between sessions, saved to `config::settings::path()`, or to `localStorage` on
+/// the web. They override the defaults and `config.toml`, and are overridden by command line
+/// options. Missing fields keep the defaults.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub ticks_per_second: Option<i32>,
+    pub rule: Option<Rule>,
+    pub color_mode: Option<ColorMode>,
+    pub age_gradient: Option<ColorGradient>,
+    /// Width and height of the world.
+    pub board_size: Option<UVec2>,
+    pub key_bindings: Vec<(Binding, Vec<KeyChord>)>,
+}
+
+impl Settings {
+    /// Load the saved settings, if any.
+    ///
+    /// Problems with the settings don't prevent startup. The defaults are used instead, and the
+    /// problems are described in `warnings`, to be logged once logging is set up.
+    pub fn load(warnings: &mut Vec<String>) -> Self {
+        match read() {
+            Ok(Some(input)) => match ron::from_str::<Self>(&input) {
+                Ok(settings) => settings.validated(warnings),
+                Err(err) => {
+                    warnings.push(format!("Ignoring corrupt settings: {err}"));
+                    Self::default()
+                }
+            },
+            Ok(None) => Self::default(),
+            Err(err) => {
+                warnings.push(format!("Failed to read settings: {err}"));
+                Self::default()
+            }
+        }
+    }
+
+    /// Drop invalid settings, so the defaults are used instead, and describe them in `warnings`.
+    fn validated(mut self, warnings: &mut Vec<String>) -> Self {
+        if let Some(tps) = self.ticks_per_second {
+            if !(config::sim::MIN_TICKS_PER_SECOND..=config::sim::MAX_TICKS_PER_SECOND)
+                .contains(&tps)
+            {
+                warnings.push(format!("Ignoring saved tick rate {tps}"));
+                self.ticks_per_second = None;
+            }
+        }
+
+        if let Some(size) = self.board_size {
+            if !(2..=config::sim::MAX_BOARD_SIZE).contains(&size.min_element())
+                || size.max_element() > config::sim::MAX_BOARD_SIZE
+            {
+                warnings.push(format!("Ignoring saved board size {size}"));
+                self.board_size = None;
+            }
+        }
+
+        self
+    }
+
+    /// Return the default key bindings, with the saved key bindings replacing them.
+    pub fn key_bindings(&self) -> KeyBindings {
+        let mut bindings = KeyBindings::default();
+        for (binding, chords) in &self.key_bindings {
+            bindings.set_all(*binding, chords.clone());
+        }
+
+        bindings
+    }
+
+    /// Return the age gradient, if saved.
+    pub fn age_gradient(&self) -> Option<AgeGradient> {
+        self.age_gradient.clone().map(AgeGradient::new)
+    }
+
+    fn to_ron(&self) -> String {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .expect("settings should be serializable")
+    }
+}
+
+
+/// Saves the settings whenever they change, and on exit.
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SavedSettings>()
+            .add_systems(
+                Update,
+                save_changed_settings.run_if(on_timer(config::settings::SAVE_INTERVAL)),
+            )
+            .add_systems(Last, save_settings_on_exit.run_if(on_event::<AppExit>()));
+    }
+}
+
+
+/// Settings as they were last saved.
+#[derive(Default, Resource)]
+struct SavedSettings(Option<String>);
+
+
+/// Everything the settings are taken from.
+#[derive(SystemParam)]
+struct CurrentSettings<'w> {
+    life: Res<'w, Life>,
+    sim_config: Res<'w, SimulationConfig>,
+    color_mode: Res<'w, ColorMode>,
+    age_gradient: Res<'w, AgeGradient>,
+    bindings: Res<'w, KeyBindings>,
+}
+
+impl CurrentSettings<'_> {
+    fn get(&self) -> Settings {
+        Settings {
+            ticks_per_second: Some(self.sim_config.ticks_per_second),
+            rule: Some(self.sim_config.rule),
+            color_mode: Some(*self.color_mode),
+            age_gradient: Some(self.age_gradient.gradient().clone()),
+            board_size: Some(self.life.bounds.size().as_uvec2()),
+            key_bindings: Binding::ALL
+                .iter()
+                .map(|binding| (*binding, self.bindings.get(*binding).to_vec()))
+                .collect(),
+        }
+    }
+}
+
+
+/// Save the settings if they changed since they were last saved.
+fn save_changed_settings(current: CurrentSettings<'_>, mut saved: ResMut<'_, SavedSettings>) {
+    let ron = current.get().to_ron();
+    if saved.0.as_ref() == Some(&ron) {
+        return;
+    }
+
+    match write(&ron) {
+        Ok(()) => debug!("Saved settings"),
+        Err(err) => error!("Failed to save settings: {err}"),
+    }
+    // Don't retry failed saves until the settings change again.
+    saved.0 = Some(ron);
+}
+
+
+fn save_settings_on_exit(current: CurrentSettings<'_>, saved: ResMut<'_, SavedSettings>) {
+    save_changed_settings(current, saved);
+}
+
+
+/// Read the saved settings from `config::settings::path()`. Returns `None` if there are none.
+#[cfg(not(target_arch = "wasm32"))]
+fn read() -> io::Result<Option<String>> {
+    let Some(path) = config::settings::path() else {
+        return Ok(None);
+    };
+
+    match std::fs::read_to_string(path) {
+        Ok(input) => Ok(Some(input)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Write the settings to `config::settings::path()`, creating its folder if needed.
+#[cfg(not(target_arch = "wasm32"))]
+fn write(ron: &str) -> io::Result<()> {
+    let Some(path) = config::settings::path() else {
+        return Err(io::Error::other("unknown config folder"));
+    };
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, ron)
+}
+
+/// There is no filesystem on the web, so the settings are kept in `localStorage`.
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> io::Result<web_sys::Storage> {
+    web_sys::window()
+        .and_then(|window| window.local_storage().ok().flatten())
+        .ok_or_else(|| io::Error::other("`localStorage` is unavailable"))
+}
+
+/// Read the saved settings from `localStorage`. Returns `None` if there are none.
+#[cfg(target_arch = "wasm32")]
+fn read() -> io::Result<Option<String>> {
+    local_storage()?
+        .get_item(config::settings::STORAGE_KEY)
+        .map_err(|err| io::Error::other(format!("{err:?}")))
+}
+
+/// Write the settings to `localStorage`.
+#[cfg(target_arch = "wasm32")]
+fn write(ron: &str) -> io::Result<()> {
+    local_storage()?
+        .set_item(config::settings::STORAGE_KEY, ron)
+        .map_err(|err| io::Error::other(format!("{err:?}")))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use super::Settings;
+    use crate::game::Rule;
+    use crate::input::bindings::{Binding, KeyChord};
+    use crate::ColorMode;
+
+    #[test]
+    pub fn test_settings_round_trip() {
+        let settings = Settings {
+            ticks_per_second: Some(10),
+            rule: Some(Rule::parse("B36/S23").unwrap()),
+            color_mode: Some(ColorMode::NeighborCount),
+            age_gradient: None,
+            board_size: Some(UVec2::new(80, 45)),
+            key_bindings: vec![(Binding::Clear, vec![KeyChord::ctrl(KeyCode::KeyX)])],
+        };
+
+        let loaded: Settings = ron::from_str(&settings.to_ron()).unwrap();
+        assert_eq!(loaded.ticks_per_second, Some(10));
+        assert_eq!(loaded.rule, settings.rule);
+        assert_eq!(loaded.color_mode, Some(ColorMode::NeighborCount));
+        assert_eq!(loaded.board_size, Some(UVec2::new(80, 45)));
+
+        // Saved key bindings replace the defaults, and are unbound from other bindings.
+        let bindings = loaded.key_bindings();
+        assert_eq!(
+            bindings.get(Binding::Clear),
+            [KeyChord::ctrl(KeyCode::KeyX)]
+        );
+        assert_eq!(bindings.get(Binding::Pause).len(), 2);
+    }
+
+    #[test]
+    pub fn test_validate_settings() {
+        // Missing fields keep the defaults.
+        let settings: Settings = ron::from_str("(ticks_per_second: Some(0))").unwrap();
+
+        let mut warnings = Vec::new();
+        let settings = settings.validated(&mut warnings);
+        assert_eq!(settings.ticks_per_second, None);
+        assert_eq!(settings.board_size, None);
+        assert!(settings.key_bindings.is_empty());
+        assert_eq!(warnings.len(), 1);
+
+        let settings: Settings = ron::from_str("(board_size: Some((0, 10)))").unwrap();
+        assert_eq!(settings.validated(&mut warnings).board_size, None);
+        assert_eq!(warnings.len(), 2);
+
+        let settings: Settings = ron::from_str("(board_size: Some((1, 10)))").unwrap();
+        assert_eq!(settings.validated(&mut warnings).board_size, None);
+        assert_eq!(warnings.len(), 3);
+    }
+}
@@ -2,42 +2,239 @@
 // Copyright (c) 2023 Martin Green. All rights reserved.
 //
 
+use bevy::diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin};
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
+use bevy::render::view::screenshot::ScreenshotManager;
+use bevy::utils::SystemTime;
+use bevy::window::PrimaryWindow;
 use bevy_egui::egui::Layout;
 use bevy_egui::{egui, EguiContexts, EguiPlugin};
 use egui_extras::{Size, StripBuilder};
 
-use crate::game::{Life, SimulationConfig, SimulationUpdateTimer};
-use crate::input::InputAction;
-use crate::{ui, AppState};
+use crate::assets::{AssetLoadFailure, GameAssets, RetryAssetLoad};
+use crate::camera::FollowLiveCells;
+use crate::color_gradient::{ColorGradient, ColorPoint};
+use crate::game::{
+    DetectedPeriod, GameLogicSet, GenerationStats, InitialPattern, Life, Neighborhood,
+    PopulationHistory, Rule, RuleVariant, RunTarget, SimulationConfig, SimulationUpdateTimer,
+    Snapshots, SoupSeed, StepTimes, Topology,
+};
+use crate::input::bindings::{Binding, KeyBindings, PendingRebind};
+use crate::input::{BrushRadius, CursorWorldPosition, DrawSymmetry, InputAction};
+use crate::{
+    config, patterns, ui, AgeColoring, AgeGradient, AppState, ColorMode, DeadColor, GlyphMode,
+    LiveColor, RenderMode, ShowGrid,
+};
 
 
 pub mod widgets;
 
 
+/// Error to show to the user in a dialog.
+#[derive(Event)]
+pub struct ShowError(pub String);
+
+
+/// Whether the pointer is over the UI, in which case mouse input is reserved for the UI.
+#[derive(Default, Resource, Deref)]
+pub struct PointerOverUi(bool);
+
+
+/// Hide the UI for the current frame, e.g. while taking a screenshot.
+#[derive(Default, Resource)]
+struct HideUi(bool);
+
+
+/// State of the controls window that isn't part of the simulation.
+struct ControlsState {
+    /// Number of generations to advance at once.
+    step_count: u32,
+    /// Generation to run the simulation until.
+    run_target: u32,
+    /// Run until the target generation as fast as possible.
+    run_fast: bool,
+    /// Width and height to resize the world to, initialized from the world bounds.
+    board_size: Option<UVec2>,
+    /// Corners to move the world bounds to, and the world bounds they were initialized from.
+    /// Forgotten when the world bounds change, e.g. as an unbounded world follows its cells.
+    bounds: Option<(IRect, IRect)>,
+    /// Rule being typed in, initialized from the current rule.
+    rule_text: Option<String>,
+    /// Why `rule_text` isn't a valid rule.
+    rule_error: Option<String>,
+    /// Live cells of each pattern in the library, parsed once for the previews.
+    library_cells: Vec<Vec<IVec2>>,
+}
+
+impl Default for ControlsState {
+    fn default() -> Self {
+        Self {
+            step_count: 100,
+            run_target: 1000,
+            run_fast: false,
+            board_size: None,
+            bounds: None,
+            rule_text: None,
+            rule_error: None,
+            library_cells: patterns::LIBRARY
+                .iter()
+                .map(|(name, rle)| match patterns::parse_rle(rle) {
+                    Ok(cells) => cells.into_iter().map(|(xy, _)| xy).collect(),
+                    Err(err) => {
+                        error!("Failed to parse pattern '{name}': {err}");
+                        Vec::new()
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+
+/// How the board is presented, as edited in the controls window.
+#[derive(SystemParam)]
+struct DisplaySettings<'w> {
+    show_grid: ResMut<'w, ShowGrid>,
+    follow: ResMut<'w, FollowLiveCells>,
+    render_mode: ResMut<'w, RenderMode>,
+    glyph_mode: ResMut<'w, GlyphMode>,
+    state_indicator: ResMut<'w, ShowStateIndicator>,
+    age_gradient: ResMut<'w, AgeGradient>,
+    color_mode: ResMut<'w, ColorMode>,
+    age_coloring: ResMut<'w, AgeColoring>,
+    live_color: ResMut<'w, LiveColor>,
+    dead_color: ResMut<'w, DeadColor>,
+}
+
+
+/// Where boards come from, besides editing them.
+#[derive(SystemParam)]
+struct BoardSources<'w> {
+    soup_seed: ResMut<'w, SoupSeed>,
+    initial_pattern: ResMut<'w, InitialPattern>,
+    snapshots: ResMut<'w, Snapshots>,
+}
+
+
+/// Measurements of the simulation, and the cell under the cursor, shown in the controls window.
+#[derive(SystemParam)]
+struct SimulationReadouts<'w> {
+    cursor: Res<'w, CursorWorldPosition>,
+    period: Res<'w, DetectedPeriod>,
+    stats: Res<'w, GenerationStats>,
+    step_times: Res<'w, StepTimes>,
+    population_history: Res<'w, PopulationHistory>,
+}
+
+
+/// Whether to show the window listing the key bindings.
+#[derive(Default, Resource)]
+struct ShowHelp(bool);
+
+
+/// Whether to show whether the simulation is paused or running in the bottom left corner.
+#[derive(Resource, Deref, DerefMut)]
+struct ShowStateIndicator(bool);
+
+impl Default for ShowStateIndicator {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+
+/// Whether to show the frame rate and the measured tick rate.
+#[derive(Default, Resource)]
+struct ShowStats(bool);
+
+
+/// Generations per second, measured over `config::ui::TPS_SAMPLE_INTERVAL`.
+#[derive(Default)]
+struct TpsMeter {
+    /// Generation and time in seconds at the start of the current sample.
+    start: Option<(u32, f32)>,
+    tps: f64,
+}
+
+impl TpsMeter {
+    /// Account for the generation at `now`, measuring the tick rate once the sample is complete.
+    /// Rewinding counts as negative ticks.
+    fn update(&mut self, generation: u32, now: f32) {
+        let Some((start_gen, start_time)) = self.start else {
+            self.start = Some((generation, now));
+            return;
+        };
+
+        let elapsed = now - start_time;
+        if elapsed >= config::ui::TPS_SAMPLE_INTERVAL {
+            let ticks = i64::from(generation) - i64::from(start_gen);
+            #[allow(clippy::cast_precision_loss)]
+            let tps = ticks as f64 / f64::from(elapsed);
+            self.tps = tps;
+            self.start = Some((generation, now));
+        }
+    }
+}
+
+
 pub struct UiPlugin;
 
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(EguiPlugin)
+        app.add_plugins((EguiPlugin, FrameTimeDiagnosticsPlugin))
+            .add_event::<ShowError>()
+            .init_resource::<HideUi>()
+            .init_resource::<ShowHelp>()
+            .init_resource::<ShowStats>()
+            .init_resource::<ShowStateIndicator>()
+            .init_resource::<PointerOverUi>()
             .add_systems(
                 PreUpdate,
                 absorb_egui_inputs
                     .after(bevy_egui::systems::process_input_system)
                     .before(bevy_egui::EguiSet::BeginFrame),
             )
-            .add_systems(Update, draw_controls_ui);
+            .add_systems(
+                Update,
+                (
+                    take_screenshot.after(GameLogicSet),
+                    draw_loading_screen.run_if(in_state(AppState::Startup)),
+                    draw_asset_error_dialog.run_if(in_state(AppState::Error)),
+                    toggle_help.run_if(on_event::<InputAction>()),
+                    toggle_stats.run_if(on_event::<InputAction>()),
+                    (
+                        draw_controls_ui,
+                        draw_help,
+                        draw_error_dialog,
+                        draw_stats.run_if(|show: Res<'_, ShowStats>| show.0),
+                        draw_state_indicator.run_if(|show: Res<'_, ShowStateIndicator>| **show),
+                    )
+                        .after(take_screenshot)
+                        .after(toggle_help)
+                        .after(toggle_stats)
+                        .run_if(|hide: Res<'_, HideUi>| !hide.0),
+                ),
+            );
     }
 }
 
 
 fn draw_controls_ui(
     state: Res<'_, State<AppState>>,
-    life: Res<'_, Life>,
+    mut life: ResMut<'_, Life>,
+    readouts: SimulationReadouts<'_>,
+    mut display: DisplaySettings<'_>,
     mut config: ResMut<'_, SimulationConfig>,
     mut timer: ResMut<'_, SimulationUpdateTimer>,
+    mut brush: ResMut<'_, BrushRadius>,
+    mut symmetry: ResMut<'_, DrawSymmetry>,
+    mut controls: Local<'_, ControlsState>,
+    mut run_target: ResMut<'_, RunTarget>,
+    mut sources: BoardSources<'_>,
     mut contexts: EguiContexts<'_, '_>,
     mut actions: EventWriter<'_, InputAction>,
+    mut errors: EventWriter<'_, ShowError>,
 ) {
     let Some(egui_ctx) = contexts.try_ctx_mut() else {
         return;
@@ -62,22 +259,685 @@ fn draw_controls_ui(
                     ui.label("Speed (tps)")
                         .on_hover_text_at_pointer("Ticks per second.");
 
-                    #[allow(clippy::cast_precision_loss)]
-                    if ui.add(egui::Slider::new(&mut tps, 1..=64)).changed() {
-                        config.ticks_per_second = tps;
-                        *timer = SimulationUpdateTimer(Timer::from_seconds(
-                            1.0 / tps as f32,
-                            TimerMode::Repeating,
-                        ));
+                    ui.horizontal(|ui| {
+                        let range =
+                            config::sim::MIN_TICKS_PER_SECOND..=config::sim::MAX_TICKS_PER_SECOND;
+                        let slider = egui::Slider::new(&mut tps, range).logarithmic(true);
+                        if ui.add_enabled(!config.unlimited, slider).changed() {
+                            config.ticks_per_second = tps;
+                            timer.retune(tps);
+                        }
+
+                        let mut unlimited = config.unlimited;
+                        if ui
+                            .checkbox(&mut unlimited, "Unlimited")
+                            .on_hover_text_at_pointer("Tick every frame, as fast as possible.")
+                            .changed()
+                        {
+                            config.unlimited = unlimited;
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Reverse").on_hover_text_at_pointer(
+                        "Rewind rather than advance on every tick, pausing once the history is \
+                         exhausted.",
+                    );
+                    ui.checkbox(&mut config.reverse, "");
+                    ui.end_row();
+
+                    let mut history_limit = life.history_limit();
+                    ui.label("History")
+                        .on_hover_text_at_pointer("Number of generations that can be rewound.");
+                    let slider =
+                        egui::Slider::new(&mut history_limit, 0..=config::sim::MAX_HISTORY_LIMIT)
+                            .logarithmic(true);
+                    if ui.add(slider).changed() {
+                        life.set_history_limit(history_limit);
+                    }
+                    ui.end_row();
+
+                    ui.label("Board size")
+                        .on_hover_text_at_pointer("Width and height of the world in cells.");
+                    ui.horizontal(|ui| {
+                        let size = controls
+                            .board_size
+                            .get_or_insert_with(|| life.bounds.size().as_uvec2());
+                        let range = 2..=config::sim::MAX_BOARD_SIZE;
+                        ui.add(egui::DragValue::new(&mut size.x).clamp_range(range.clone()));
+                        ui.label("x");
+                        ui.add(egui::DragValue::new(&mut size.y).clamp_range(range));
+                        if ui.button("Resize").clicked() {
+                            actions.send(InputAction::ResizeBoard {
+                                width: size.x,
+                                height: size.y,
+                            });
+                            // Odd sizes are rounded down, so read the size back from the world.
+                            controls.board_size = None;
+                            controls.bounds = None;
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Bounds").on_hover_text_at_pointer(
+                        "Minimum and maximum corners of the world. The maximum is exclusive.",
+                    );
+                    ui.horizontal(|ui| {
+                        if controls.bounds.is_some_and(|(_, base)| base != life.bounds) {
+                            controls.bounds = None;
+                        }
+                        let (bounds, _) = controls.bounds.get_or_insert((life.bounds, life.bounds));
+                        // Unbounded worlds follow their cells anywhere, so only the size is
+                        // limited, and the corners just enough for it not to overflow.
+                        let limit = i32::MAX / 2;
+                        for corner in [&mut bounds.min, &mut bounds.max] {
+                            ui.add(egui::DragValue::new(&mut corner.x).clamp_range(-limit..=limit));
+                            ui.add(egui::DragValue::new(&mut corner.y).clamp_range(-limit..=limit));
+                        }
+
+                        // Keep the sizes the board size fields allow, so no corner edit spawns
+                        // an enormous number of sprites.
+                        let size = bounds.size();
+                        #[allow(clippy::cast_possible_wrap)]
+                        let range = 2..=config::sim::MAX_BOARD_SIZE as i32;
+                        let valid = range.contains(&size.x) && range.contains(&size.y);
+                        if valid {
+                            ui.label(format!("{} x {} cells", size.x, size.y));
+                        } else {
+                            ui.colored_label(
+                                ui.visuals().error_fg_color,
+                                format!("Sides must be within {}..={}", range.start(), range.end()),
+                            );
+                        }
+                        let bounds = *bounds;
+                        if ui.add_enabled(valid, egui::Button::new("Apply")).clicked() {
+                            actions.send(InputAction::SetBounds(bounds));
+                            controls.board_size = None;
+                            controls.bounds = None;
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Rule").on_hover_text_at_pointer(
+                        "Takes effect on the next tick, keeping the board.",
+                    );
+                    let presets = Rule::PRESETS.map(|(name, rule)| (name, Rule::parse(rule)));
+                    let current = config.rule.to_string();
+                    let selected = presets
+                        .iter()
+                        .find(|(_, rule)| rule.as_ref() == Ok(&config.rule))
+                        .map_or(current.as_str(), |(name, _)| *name);
+                    egui::ComboBox::from_id_source("rule")
+                        .selected_text(selected)
+                        .show_ui(ui, |ui| {
+                            for (name, rule) in &presets {
+                                let Ok(rule) = rule else {
+                                    continue;
+                                };
+                                ui.selectable_value(&mut config.rule, *rule, *name)
+                                    .on_hover_text_at_pointer(rule.to_string());
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Soup seed").on_hover_text_at_pointer(
+                        "The same seed always generates the same random soup.",
+                    );
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut sources.soup_seed.0));
+                        for (label, reroll) in [("Randomize", false), ("Reroll", true)] {
+                            if ui.button(label).clicked() {
+                                actions.send(InputAction::RandomizeBoard {
+                                    density: config::sim::DEFAULT_SOUP_DENSITY,
+                                    reroll,
+                                });
+                            }
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Start pattern")
+                        .on_hover_text_at_pointer("Pattern the simulation starts with when reset.");
+                    ui.horizontal(|ui| {
+                        let mut pattern = sources.initial_pattern.clone();
+                        egui::ComboBox::from_id_source("initial_pattern")
+                            .selected_text(pattern.label())
+                            .show_ui(ui, |ui| {
+                                let presets =
+                                    patterns::LIBRARY.map(|(name, _)| InitialPattern::Preset(name));
+                                for value in [
+                                    InitialPattern::Empty,
+                                    InitialPattern::Butterfly,
+                                    InitialPattern::Random,
+                                ]
+                                .into_iter()
+                                .chain(presets)
+                                {
+                                    let label = value.label();
+                                    ui.selectable_value(&mut pattern, value, label);
+                                }
+                            });
+                        sources.initial_pattern.set_if_neq(pattern);
+
+                        if ui
+                            .button("Reset")
+                            .on_hover_text_at_pointer(
+                                "Replace the board with the start pattern. Forgets the history.",
+                            )
+                            .clicked()
+                        {
+                            actions.send(InputAction::ResetSimulation);
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Custom rule").on_hover_text_at_pointer(
+                        "Rule in the B/S notation, e.g. B36/S23. Press Enter to apply.",
+                    );
+                    ui.vertical(|ui| {
+                        let ControlsState {
+                            rule_text,
+                            rule_error,
+                            ..
+                        } = &mut *controls;
+                        let text = rule_text.get_or_insert_with(|| config.rule.to_string());
+
+                        let response = ui.add(
+                            egui::TextEdit::singleline(text)
+                                .desired_width(ui.spacing().slider_width),
+                        );
+                        if response.changed() || response.lost_focus() {
+                            match Rule::parse(text) {
+                                Ok(rule) => {
+                                    *rule_error = None;
+                                    if response.lost_focus() {
+                                        config.rule = rule;
+                                    }
+                                }
+                                Err(err) => *rule_error = Some(err.to_string()),
+                            }
+                        }
+                        // Follow rule changes elsewhere, e.g. choosing a preset, unless editing.
+                        if !response.has_focus() && rule_error.is_none() {
+                            *text = config.rule.to_string();
+                        }
+
+                        if let Some(err) = rule_error {
+                            ui.colored_label(ui.visuals().error_fg_color, err.as_str());
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Variant");
+                    let label = |variant: RuleVariant| match variant {
+                        RuleVariant::Standard => "Standard",
+                        RuleVariant::Immigration => "Immigration",
+                    };
+                    egui::ComboBox::from_id_source("variant")
+                        .selected_text(label(config.variant))
+                        .show_ui(ui, |ui| {
+                            for variant in [RuleVariant::Standard, RuleVariant::Immigration] {
+                                ui.selectable_value(&mut config.variant, variant, label(variant));
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Neighborhood");
+                    let label = |neighborhood: Neighborhood| match neighborhood {
+                        Neighborhood::Moore => "Moore",
+                        Neighborhood::VonNeumann => "von Neumann",
+                    };
+                    egui::ComboBox::from_id_source("neighborhood")
+                        .selected_text(label(config.neighborhood))
+                        .show_ui(ui, |ui| {
+                            for neighborhood in [Neighborhood::Moore, Neighborhood::VonNeumann] {
+                                ui.selectable_value(
+                                    &mut config.neighborhood,
+                                    neighborhood,
+                                    label(neighborhood),
+                                );
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Edges").on_hover_text_at_pointer(
+                        "Wrap around, stop at walls, or grow the world with the live cells.",
+                    );
+                    let label = |topology: Topology| match topology {
+                        Topology::Torus => "Wrap",
+                        Topology::Wall => "Wall",
+                        Topology::Unbounded => "Unbounded",
+                    };
+                    egui::ComboBox::from_id_source("topology")
+                        .selected_text(label(config.topology))
+                        .show_ui(ui, |ui| {
+                            for topology in [Topology::Torus, Topology::Wall, Topology::Unbounded] {
+                                ui.selectable_value(
+                                    &mut config.topology,
+                                    topology,
+                                    label(topology),
+                                );
+                            }
+                        });
+                    ui.end_row();
+
+                    // Shortcut for the most common switch of `Edges`, taking effect on the next
+                    // tick without touching the board.
+                    ui.label("Wrap edges").on_hover_text_at_pointer(
+                        "Wrap around the edges of the world, or stop at walls otherwise.",
+                    );
+                    let mut wrap = config.topology == Topology::Torus;
+                    if ui.checkbox(&mut wrap, "").changed() {
+                        config.topology = if wrap {
+                            Topology::Torus
+                        } else {
+                            Topology::Wall
+                        };
+                    }
+                    ui.end_row();
+
+                    ui.label("Brush radius").on_hover_text_at_pointer(
+                        "Cells around the cursor painted or erased at once.",
+                    );
+                    ui.add(egui::Slider::new(
+                        &mut brush.0,
+                        0..=config::editor::MAX_BRUSH_RADIUS,
+                    ));
+                    ui.end_row();
+
+                    ui.label("Symmetry");
+                    let label = |symmetry: DrawSymmetry| match symmetry {
+                        DrawSymmetry::None => "None",
+                        DrawSymmetry::Horizontal => "Horizontal",
+                        DrawSymmetry::Vertical => "Vertical",
+                        DrawSymmetry::Quad => "Quad",
+                    };
+                    egui::ComboBox::from_id_source("symmetry")
+                        .selected_text(label(*symmetry))
+                        .show_ui(ui, |ui| {
+                            for value in [
+                                DrawSymmetry::None,
+                                DrawSymmetry::Horizontal,
+                                DrawSymmetry::Vertical,
+                                DrawSymmetry::Quad,
+                            ] {
+                                ui.selectable_value(&mut *symmetry, value, label(value));
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Show grid");
+                    ui.checkbox(&mut display.show_grid, "");
+                    ui.end_row();
+
+                    ui.label("Follow")
+                        .on_hover_text_at_pointer("Keep the camera centered on the live cells.");
+                    ui.checkbox(&mut display.follow, "");
+                    ui.end_row();
+
+                    ui.label("Show state")
+                        .on_hover_text_at_pointer("Show whether the simulation is paused.");
+                    ui.checkbox(&mut display.state_indicator, "");
+                    ui.end_row();
+
+                    ui.label("Render mode").on_hover_text_at_pointer(
+                        "Glyphs look best, a texture is faster for large boards.",
+                    );
+                    let label = |mode: RenderMode| match mode {
+                        RenderMode::Glyphs => "Glyphs",
+                        RenderMode::Texture => "Texture",
+                    };
+                    // Only touch the resource on change, as changing it rebuilds the presentation.
+                    let mut mode = *display.render_mode;
+                    egui::ComboBox::from_id_source("render_mode")
+                        .selected_text(label(mode))
+                        .show_ui(ui, |ui| {
+                            for value in [RenderMode::Glyphs, RenderMode::Texture] {
+                                ui.selectable_value(&mut mode, value, label(value));
+                            }
+                        });
+                    display.render_mode.set_if_neq(mode);
+                    ui.end_row();
+
+                    ui.label("Cell glyph").on_hover_text_at_pointer(
+                        "Draw live cells as blocks, or as the digit of their age, up to 9.",
+                    );
+                    let label = |mode: GlyphMode| match mode {
+                        GlyphMode::Block => "Block",
+                        GlyphMode::AgeDigit => "Age digit",
+                    };
+                    ui.add_enabled_ui(*display.render_mode == RenderMode::Glyphs, |ui| {
+                        egui::ComboBox::from_id_source("glyph_mode")
+                            .selected_text(label(*display.glyph_mode))
+                            .show_ui(ui, |ui| {
+                                for value in [GlyphMode::Block, GlyphMode::AgeDigit] {
+                                    ui.selectable_value(
+                                        &mut *display.glyph_mode,
+                                        value,
+                                        label(value),
+                                    );
+                                }
+                            });
+                    });
+                    ui.end_row();
+
+                    ui.label("Color by");
+                    ui.vertical(|ui| {
+                        let label = |mode: ColorMode| match mode {
+                            ColorMode::Age => "Age",
+                            ColorMode::NeighborCount => "Neighbor count",
+                        };
+                        let mut mode = *display.color_mode;
+                        egui::ComboBox::from_id_source("color_mode")
+                            .selected_text(label(mode))
+                            .show_ui(ui, |ui| {
+                                for value in [ColorMode::Age, ColorMode::NeighborCount] {
+                                    ui.selectable_value(&mut mode, value, label(value));
+                                }
+                            });
+                        display.color_mode.set_if_neq(mode);
+
+                        if mode == ColorMode::NeighborCount {
+                            neighbor_count_legend(ui);
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Age coloring")
+                        .on_hover_text_at_pointer("Color live cells by their age.");
+                    let mut age_coloring = **display.age_coloring;
+                    if ui.checkbox(&mut age_coloring, "").changed() {
+                        **display.age_coloring = age_coloring;
                     }
                     ui.end_row();
 
+                    if age_coloring {
+                        ui.label("Newborn color")
+                            .on_hover_text_at_pointer("Color the age gradient starts at.");
+                        if let Some(first) =
+                            display.age_gradient.gradient().points().first().copied()
+                        {
+                            let mut color = first.value();
+                            if ui.add(ui::widgets::color_picker(&mut color)).changed() {
+                                let mut gradient = display.age_gradient.gradient().clone();
+                                gradient.insert(ColorPoint::new(first.point(), color));
+                                display.age_gradient.set(gradient);
+                            }
+                        }
+                    } else {
+                        ui.label("Live color")
+                            .on_hover_text_at_pointer("Color of all live cells.");
+                        let mut color = display.live_color.0;
+                        if ui.add(ui::widgets::color_picker(&mut color)).changed() {
+                            display.live_color.0 = color;
+                        }
+                    }
+                    ui.end_row();
+
+                    ui.label("Oldest color").on_hover_text_at_pointer(
+                        "Color the age gradient ends at, approaching the dead color.",
+                    );
+                    if let Some(last) = display.age_gradient.gradient().points().last().copied() {
+                        let mut color = last.value();
+                        if ui.add(ui::widgets::color_picker(&mut color)).changed() {
+                            let mut gradient = display.age_gradient.gradient().clone();
+                            gradient.insert(ColorPoint::new(last.point(), color));
+                            display.age_gradient.set(gradient);
+                        }
+                    }
+                    ui.end_row();
+
+                    ui.label("Dead color");
+                    let mut color = display.dead_color.0;
+                    if ui.add(ui::widgets::color_picker(&mut color)).changed() {
+                        display.dead_color.0 = color;
+                    }
+                    ui.end_row();
+
+                    ui.label("Pause on still life");
+                    ui.checkbox(&mut config.detect_stable, "");
+                    ui.end_row();
+
+                    ui.label("Pause on oscillation");
+                    ui.checkbox(&mut config.detect_oscillation, "");
+                    ui.end_row();
+
+                    ui.label("Pause on extinction");
+                    ui.checkbox(&mut config.detect_extinction, "");
+                    ui.end_row();
+
+                    ui.label("Step many")
+                        .on_hover_text_at_pointer("Advance many generations at once.");
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::DragValue::new(&mut controls.step_count)
+                                .clamp_range(1..=config::sim::MAX_STEP_COUNT),
+                        );
+                        if ui.button("Step").clicked() {
+                            actions.send(InputAction::PauseSimulation);
+                            actions.send(InputAction::StepMany(controls.step_count));
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Run until").on_hover_text_at_pointer(
+                        "Run until the generation is reached, then pause.",
+                    );
+                    ui.horizontal(|ui| {
+                        if let Some(generation) = run_target.generation {
+                            ui.label(format!("{generation}"));
+                            if ui.button("Cancel").clicked() {
+                                run_target.generation = None;
+                            }
+                        } else {
+                            ui.add(egui::DragValue::new(&mut controls.run_target));
+                            ui.checkbox(&mut controls.run_fast, "Fast")
+                                .on_hover_text_at_pointer("Ignore the tick rate.");
+                            if ui.button("Run").clicked() {
+                                *run_target = RunTarget {
+                                    generation: Some(controls.run_target),
+                                    fast: controls.run_fast,
+                                };
+                            }
+                        }
+                    });
+                    ui.end_row();
+
                     let gen = life.generation;
                     ui.label("Generation");
-                    ui.label(format!("{gen}"));
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{gen}"));
+                        if ui
+                            .small_button("Reset gen")
+                            .on_hover_text_at_pointer(
+                                "Treat the current cells as generation 0. Forgets the history.",
+                            )
+                            .clicked()
+                        {
+                            actions.send(InputAction::ResetGeneration);
+                        }
+                    });
+                    ui.end_row();
+
+                    let population = life.population();
+                    ui.label("Population");
+                    ui.label(format!("{population}"));
+                    ui.end_row();
+
+                    let live_bounds = life.live_bounds();
+                    ui.label("Bounding box")
+                        .on_hover_text_at_pointer("Extent of the live cells, corners inclusive.");
+                    match live_bounds {
+                        Some(bounds) => ui.label(format!("{} to {}", bounds.min, bounds.max)),
+                        None => ui.label("-"),
+                    };
+                    ui.end_row();
+
+                    ui.label("Density").on_hover_text_at_pointer(
+                        "Population divided by the area of the bounding box.",
+                    );
+                    match live_bounds {
+                        Some(bounds) => {
+                            let size = (bounds.size() + IVec2::ONE).as_vec2();
+                            #[allow(clippy::cast_precision_loss)]
+                            let density = population as f32 / (size.x * size.y);
+                            ui.label(format!("{:.1}%", density * 100.0))
+                        }
+                        None => ui.label("-"),
+                    };
+                    ui.end_row();
+
+                    ui.label("Births / deaths")
+                        .on_hover_text_at_pointer("Cells born and died in the last generation.");
+                    ui.horizontal(|ui| {
+                        ui.label(format!(
+                            "{} / {}",
+                            readouts.stats.births, readouts.stats.deaths
+                        ));
+
+                        let export = ui
+                            .add_enabled(
+                                !readouts.population_history.is_empty(),
+                                egui::Button::new("Export CSV").small(),
+                            )
+                            .on_hover_text_at_pointer(format!(
+                                "Export the statistics of the last {} generations to '{}'.",
+                                readouts.population_history.len(),
+                                config::save::STATS_PATH
+                            ));
+                        if export.clicked() {
+                            actions.send(InputAction::ExportStats);
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Period").on_hover_text_at_pointer(
+                        "Distance to the most recent identical generation in the history.",
+                    );
+                    match **readouts.period {
+                        Some(period) => ui.label(format!("{period}")),
+                        None => ui.label("-"),
+                    };
+                    ui.end_row();
+
+                    ui.label("Step time").on_hover_text_at_pointer(format!(
+                        "Time to advance the last generation, and the average over the last {}.",
+                        config::sim::STEP_TIME_SAMPLES
+                    ));
+                    match (readouts.step_times.last(), readouts.step_times.average()) {
+                        (Some(last), Some(average)) => ui.label(format!(
+                            "{} µs / avg {} µs",
+                            last.as_micros(),
+                            average.as_micros()
+                        )),
+                        _ => ui.label("-"),
+                    };
+                    ui.end_row();
+
+                    let hovered = readouts.cursor.cell();
+                    ui.label("Hovered cell")
+                        .on_hover_text_at_pointer("Cell under the cursor, and its state.");
+                    match (life.age_of(hovered), life.cells.get(&hovered)) {
+                        (Some(age), _) => ui.label(format!("{hovered}: alive, age {age}")),
+                        (None, Some(cell)) => {
+                            ui.label(format!("{hovered}: dying, state {}", cell.state))
+                        }
+                        (None, None) => ui.label(format!("{hovered}: dead")),
+                    };
                     ui.end_row();
                 });
 
+            ui.collapsing("Patterns", |ui| {
+                for ((name, rle), cells) in patterns::LIBRARY.iter().zip(&controls.library_cells) {
+                    ui.horizontal(|ui| {
+                        ui.add(ui::widgets::pattern_preview(cells));
+                        if ui
+                            .button(*name)
+                            .on_hover_text_at_pointer("Copy to the clipboard. Paste with Ctrl + V.")
+                            .clicked()
+                        {
+                            actions.send(InputAction::CopyPattern(*rle));
+                        }
+                    });
+                }
+            });
+
+            ui.collapsing("Snapshots", |ui| {
+                ui.horizontal(|ui| {
+                    if ui
+                        .button("Take")
+                        .on_hover_text_at_pointer("Keep the board as a snapshot.")
+                        .clicked()
+                    {
+                        actions.send(InputAction::TakeSnapshot);
+                    }
+                    ui.add_enabled_ui(!sources.snapshots.is_empty(), |ui| {
+                        if ui.button("Previous").clicked() {
+                            actions.send(InputAction::CycleSnapshot { next: false });
+                        }
+                        if ui.button("Next").clicked() {
+                            actions.send(InputAction::CycleSnapshot { next: true });
+                        }
+                    });
+                    match sources.snapshots.current() {
+                        Some(current) => {
+                            ui.label(format!("{} / {}", current + 1, sources.snapshots.len()))
+                        }
+                        None => ui.label("-"),
+                    };
+                });
+
+                let mut reset_generation = sources.snapshots.reset_generation;
+                if ui
+                    .checkbox(&mut reset_generation, "Reset generation on load")
+                    .changed()
+                {
+                    sources.snapshots.reset_generation = reset_generation;
+                }
+            });
+
+            ui.collapsing("Age gradient", |ui| {
+                let mut gradient = display.age_gradient.gradient().clone();
+                if ui
+                    .add(ui::widgets::gradient_editor(&mut gradient))
+                    .changed()
+                {
+                    display.age_gradient.set(gradient);
+                }
+
+                // Saving and loading files isn't supported on the web.
+                if cfg!(target_arch = "wasm32") {
+                    return;
+                }
+
+                let path = std::path::Path::new(config::cells::GRADIENT_PATH);
+                ui.horizontal(|ui| {
+                    if ui.button("Save").clicked() {
+                        match display.age_gradient.gradient().save(path) {
+                            Ok(()) => info!("Saved gradient to '{}'", path.display()),
+                            Err(err) => {
+                                error!("Failed to save gradient to '{}': {err}", path.display());
+                            }
+                        }
+                    }
+                    if ui.button("Load").clicked() {
+                        match ColorGradient::load(path) {
+                            Ok(gradient) => {
+                                info!("Loaded gradient from '{}'", path.display());
+                                display.age_gradient.set(gradient);
+                            }
+                            Err(err) => {
+                                error!("Failed to load gradient from '{}': {err}", path.display());
+                                errors.send(ShowError(format!(
+                                    "Failed to load gradient from '{}': {err}",
+                                    path.display()
+                                )));
+                            }
+                        }
+                    }
+                });
+            });
+
             ui.separator();
 
             let vh = ui.spacing().interact_size.y;
@@ -86,20 +946,42 @@ fn draw_controls_ui(
                 .sizes(Size::exact(vh), 1)
                 .vertical(|mut strip| {
                     strip.strip(|builder| {
-                        builder.sizes(Size::remainder(), 2).horizontal(|mut strip| {
+                        builder.sizes(Size::remainder(), 4).horizontal(|mut strip| {
                             strip.cell(|ui| {
-                                if ui.button("Rewind").clicked() {
+                                let button = ui
+                                    .add_enabled(
+                                        !life.history.is_empty(),
+                                        egui::Button::new("Rewind"),
+                                    )
+                                    .on_disabled_hover_text("No history to rewind.");
+                                if button.clicked() {
                                     actions.send(InputAction::PauseSimulation);
                                     actions.send(InputAction::RewindSimulation);
                                 }
                             });
 
+                            strip.cell(|ui| {
+                                let button = ui
+                                    .add_enabled(!life.redo.is_empty(), egui::Button::new("Redo"))
+                                    .on_disabled_hover_text("Nothing to redo.");
+                                if button.clicked() {
+                                    actions.send(InputAction::PauseSimulation);
+                                    actions.send(InputAction::RedoSimulation);
+                                }
+                            });
+
                             strip.cell(|ui| {
                                 if ui.button("Advance").clicked() {
                                     actions.send(InputAction::PauseSimulation);
                                     actions.send(InputAction::AdvanceSimulation);
                                 }
                             });
+
+                            strip.cell(|ui| {
+                                if ui.button("Clear").clicked() {
+                                    actions.send(InputAction::ClearBoard);
+                                }
+                            });
                         });
                     });
                 })
@@ -116,9 +998,305 @@ fn draw_controls_ui(
 }
 
 
+/// Save a screenshot of the primary window to a timestamped PNG file in the working directory. On
+/// the web, the screenshot is downloaded instead.
+fn take_screenshot(
+    q_window: Query<'_, '_, Entity, With<PrimaryWindow>>,
+    mut screenshots: ResMut<'_, ScreenshotManager>,
+    mut hide: ResMut<'_, HideUi>,
+    mut actions: EventReader<'_, '_, InputAction>,
+) {
+    hide.0 = false;
+
+    for action in actions.read() {
+        if let InputAction::Screenshot = action {
+            let Ok(window) = q_window.get_single() else {
+                warn!("No primary window");
+                return;
+            };
+
+            let timestamp = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis();
+            let path = format!("screenshot-{timestamp}.png");
+
+            match screenshots.save_screenshot_to_disk(window, &path) {
+                Ok(()) => {
+                    info!("Saving screenshot to '{path}'");
+                    hide.0 = config::screenshot::HIDE_UI;
+                }
+                Err(_) => warn!("A screenshot is already being taken"),
+            }
+        }
+    }
+}
+
+
+fn toggle_help(mut show: ResMut<'_, ShowHelp>, mut actions: EventReader<'_, '_, InputAction>) {
+    for action in actions.read() {
+        if let InputAction::ToggleHelp = action {
+            show.0 = !show.0;
+        }
+    }
+}
+
+
+fn toggle_stats(mut show: ResMut<'_, ShowStats>, mut actions: EventReader<'_, '_, InputAction>) {
+    for action in actions.read() {
+        if let InputAction::ToggleStats = action {
+            show.0 = !show.0;
+        }
+    }
+}
+
+
+/// Show the measured frame rate and tick rate, next to the requested tick rate, in the top right
+/// corner.
+fn draw_stats(
+    diagnostics: Res<'_, DiagnosticsStore>,
+    time: Res<'_, Time<Real>>,
+    life: Res<'_, Life>,
+    config: Res<'_, SimulationConfig>,
+    mut meter: Local<'_, TpsMeter>,
+    mut contexts: EguiContexts<'_, '_>,
+) {
+    let Some(egui_ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    meter.update(life.generation, time.elapsed_seconds());
+    let fps = diagnostics
+        .get(&FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(bevy::diagnostic::Diagnostic::smoothed);
+
+    egui::Window::new("Stats")
+        .title_bar(false)
+        .resizable(false)
+        .anchor(egui::Align2::RIGHT_TOP, [-8.0, 8.0])
+        .show(egui_ctx, |ui| {
+            egui::Grid::new("stats").num_columns(2).show(ui, |ui| {
+                ui.label("FPS");
+                ui.label(fps.map_or_else(|| String::from("-"), |fps| format!("{fps:.0}")));
+                ui.end_row();
+
+                ui.label("TPS")
+                    .on_hover_text_at_pointer("Measured / requested ticks per second.");
+                if config.unlimited {
+                    ui.label(format!("{:.1} / unlimited", meter.tps));
+                } else {
+                    ui.label(format!("{:.1} / {}", meter.tps, config.ticks_per_second));
+                }
+                ui.end_row();
+            });
+        });
+}
+
+
+/// Draw a swatch of the color of each number of live neighbors, labeled with the number.
+fn neighbor_count_legend(ui: &mut egui::Ui) {
+    ui.horizontal(|ui| {
+        ui.spacing_mut().item_spacing.x = 2.0;
+        for (count, color) in config::cells::NEIGHBOR_COUNT_COLORS.iter().enumerate() {
+            let fill = ui::widgets::to_color32(*color);
+            let text = egui::RichText::new(format!("{count}"))
+                .monospace()
+                .color(egui::Color32::BLACK)
+                .background_color(fill);
+            ui.label(text)
+                .on_hover_text_at_pointer(format!("{count} live neighbors"));
+        }
+    });
+}
+
+
+/// Show whether the simulation is paused or running in the bottom left corner, even when the
+/// controls window is collapsed.
+fn draw_state_indicator(state: Res<'_, State<AppState>>, mut contexts: EguiContexts<'_, '_>) {
+    let Some(egui_ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    let (glyph, color) = match state.get() {
+        AppState::Running => ("▶", egui::Color32::LIGHT_GREEN),
+        AppState::Paused => ("⏸", egui::Color32::YELLOW),
+        _ => return,
+    };
+
+    egui::Area::new(egui::Id::new("state_indicator"))
+        .anchor(egui::Align2::LEFT_BOTTOM, [8.0, -8.0])
+        .interactable(false)
+        .show(egui_ctx, |ui| {
+            ui.label(egui::RichText::new(glyph).size(24.0).color(color));
+        });
+}
+
+
+/// List the key bindings. Clicking a binding rebinds it to the next key press.
+fn draw_help(
+    mut show: ResMut<'_, ShowHelp>,
+    mut bindings: ResMut<'_, KeyBindings>,
+    mut pending: ResMut<'_, PendingRebind>,
+    mut contexts: EguiContexts<'_, '_>,
+) {
+    let Some(egui_ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    egui::Window::new("Help")
+        .open(&mut show.0)
+        .resizable(false)
+        .collapsible(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(egui_ctx, |ui| {
+            egui::Grid::new("help")
+                .num_columns(2)
+                .spacing([20.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    for binding in Binding::ALL {
+                        let text = if **pending == Some(binding) {
+                            String::from("Press a key...")
+                        } else {
+                            let keys = bindings
+                                .get(binding)
+                                .iter()
+                                .map(ToString::to_string)
+                                .collect::<Vec<_>>();
+                            if keys.is_empty() {
+                                String::from("-")
+                            } else {
+                                keys.join(", ")
+                            }
+                        };
+
+                        if ui
+                            .button(text)
+                            .on_hover_text_at_pointer("Click to rebind. Escape cancels.")
+                            .clicked()
+                        {
+                            **pending = Some(binding);
+                        }
+                        ui.label(binding.description());
+                        ui.end_row();
+                    }
+                });
+
+            ui.separator();
+
+            ui.vertical_centered(|ui| {
+                if ui.button("Reset to defaults").clicked() {
+                    *bindings = KeyBindings::default();
+                    **pending = None;
+                }
+            });
+        });
+
+    // Cancel rebinding when the window is closed.
+    if !show.0 && pending.is_some() {
+        **pending = None;
+    }
+}
+
+
+/// Show the progress of loading the assets, so a slow load doesn't look like a hang.
+fn draw_loading_screen(
+    asset_server: Res<'_, AssetServer>,
+    assets: Res<'_, GameAssets>,
+    mut contexts: EguiContexts<'_, '_>,
+) {
+    let Some(egui_ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    let loaded = assets
+        .iter()
+        .filter(|handle| asset_server.is_loaded_with_dependencies(handle.id()))
+        .count();
+
+    egui::Window::new("Loading")
+        .title_bar(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(egui_ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label(format!("Loading assets... {loaded} / {}", assets.len()));
+            });
+        });
+}
+
+
+/// Name the asset that failed to load, offering to load it again.
+fn draw_asset_error_dialog(
+    failure: Option<Res<'_, AssetLoadFailure>>,
+    mut contexts: EguiContexts<'_, '_>,
+    mut ev_retry: EventWriter<'_, RetryAssetLoad>,
+) {
+    let Some(egui_ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+    let Some(failure) = failure else {
+        return;
+    };
+
+    egui::Window::new("Failed to load assets")
+        .resizable(false)
+        .collapsible(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(egui_ctx, |ui| {
+            ui.label(format!(
+                "Failed to load '{}': {}",
+                failure.path, failure.error
+            ));
+            ui.label("Make sure the `assets` folder is next to the executable.");
+            ui.vertical_centered(|ui| {
+                if ui.button("Retry").clicked() {
+                    ev_retry.send(RetryAssetLoad);
+                }
+            });
+        });
+}
+
+
+/// Show errors one at a time until dismissed.
+fn draw_error_dialog(
+    mut errors: Local<'_, Vec<String>>,
+    mut ev_errors: EventReader<'_, '_, ShowError>,
+    mut contexts: EguiContexts<'_, '_>,
+) {
+    errors.extend(ev_errors.read().map(|error| error.0.clone()));
+
+    let Some(error) = errors.first() else {
+        return;
+    };
+    let Some(egui_ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    let mut dismissed = false;
+    egui::Window::new("Error")
+        .resizable(false)
+        .collapsible(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(egui_ctx, |ui| {
+            ui.label(error);
+            ui.vertical_centered(|ui| {
+                dismissed = ui.button("OK").clicked();
+            });
+        });
+
+    if dismissed {
+        errors.remove(0);
+    }
+}
+
+
 // @CREDIT: <https://github.com/mvlabat/bevy_egui/issues/47#issuecomment-1703964969>
 fn absorb_egui_inputs(
     mut mouse: ResMut<'_, ButtonInput<MouseButton>>,
+    mut keys: ResMut<'_, ButtonInput<KeyCode>>,
+    mut pointer_over_ui: ResMut<'_, PointerOverUi>,
     mut contexts: EguiContexts<'_, '_>,
 ) {
     let Some(egui_ctx) = contexts.try_ctx_mut() else {
@@ -126,7 +1304,14 @@ fn absorb_egui_inputs(
         return;
     };
 
-    if egui_ctx.is_pointer_over_area() {
+    pointer_over_ui.0 = egui_ctx.is_pointer_over_area();
+    if pointer_over_ui.0 {
         mouse.reset_all();
     }
+
+    // Keys typed into a text field or drag value mustn't trigger key bindings, e.g. `C` of a
+    // "Generations" rule clearing the board, so no `InputAction` is sent while egui has focus.
+    if egui_ctx.wants_keyboard_input() {
+        keys.reset_all();
+    }
 }
@@ -1,3 +1,9 @@
+mod color_picker;
+mod gradient_editor;
+mod pattern_preview;
 mod toggle;
 
+pub use color_picker::{color_picker, to_color32};
+pub use gradient_editor::gradient_editor;
+pub use pattern_preview::pattern_preview;
 pub use toggle::toggle;
@@ -0,0 +1,37 @@
+//
+// Copyright (c) 2023 Martin Green. All rights reserved.
+//
+
+use bevy::color::Srgba;
+use bevy_egui::egui;
+
+use crate::color_gradient::to_u8;
+
+
+pub fn to_color32(color: Srgba) -> egui::Color32 {
+    egui::Color32::from_rgba_unmultiplied(
+        to_u8(color.red),
+        to_u8(color.green),
+        to_u8(color.blue),
+        to_u8(color.alpha),
+    )
+}
+
+
+fn color_picker_widget(ui: &mut egui::Ui, color: &mut Srgba) -> egui::Response {
+    let mut rgb = [to_u8(color.red), to_u8(color.green), to_u8(color.blue)];
+    let response = ui.color_edit_button_srgb(&mut rgb);
+    if response.changed() {
+        let [red, green, blue] = rgb;
+        *color = Srgba::rgb_u8(red, green, blue);
+    }
+
+    response
+}
+
+
+/// Button showing an opaque color, opening a color picker when clicked. The response is marked as
+/// changed whenever the color is changed.
+pub fn color_picker(color: &mut Srgba) -> impl egui::Widget + '_ {
+    move |ui: &mut egui::Ui| color_picker_widget(ui, color)
+}
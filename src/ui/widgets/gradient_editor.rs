@@ -0,0 +1,195 @@
+//
+// Copyright (c) 2023 Martin Green. All rights reserved.
+//
+
+use bevy_egui::egui;
+
+use super::color_picker::{color_picker, to_color32};
+use crate::color_gradient::{ColorGradient, ColorPoint};
+
+
+/// Number of slices the preview bar is drawn with.
+const PREVIEW_STEPS: usize = 64;
+
+/// Smallest distance between two sampling points, so moving one never replaces another.
+const MIN_SPACING: f32 = 0.001;
+
+
+/// Move the `i`th sampling point to `point`, clamped between its neighbors so the order of the
+/// sampling points is kept. Returns `true` if the point moved.
+#[allow(clippy::float_cmp)]
+fn move_point(gradient: &mut ColorGradient, i: usize, point: f32) -> bool {
+    let points = gradient.points();
+    let current = points[i];
+
+    let min = i
+        .checked_sub(1)
+        .map_or(0.0, |prev| points[prev].point() + MIN_SPACING);
+    let max = points
+        .get(i + 1)
+        .map_or(1.0, |next| next.point() - MIN_SPACING);
+    let point = point.max(min).min(max);
+
+    if point == current.point() {
+        return false;
+    }
+
+    gradient.remove(current.point());
+    gradient.insert(ColorPoint::new(point, current.value()));
+
+    true
+}
+
+
+/// Draw the gradient with a draggable handle below each sampling point. Returns `true` if a
+/// sampling point was moved.
+fn preview_bar(ui: &mut egui::Ui, gradient: &mut ColorGradient) -> bool {
+    let bar_height = ui.spacing().interact_size.y;
+    let handle_size = bar_height * 0.5;
+    let width = ui.spacing().slider_width * 1.5;
+
+    let (rect, response) = ui.allocate_exact_size(
+        egui::vec2(width, bar_height + handle_size),
+        egui::Sense::hover(),
+    );
+    let bar = egui::Rect::from_min_size(rect.min, egui::vec2(width, bar_height));
+
+    #[allow(clippy::cast_precision_loss)]
+    let slice = width / PREVIEW_STEPS as f32;
+    for i in 0..PREVIEW_STEPS {
+        #[allow(clippy::cast_precision_loss)]
+        let x = i as f32 * slice;
+        let color = gradient.sample((x + slice * 0.5) / width);
+        ui.painter().rect_filled(
+            egui::Rect::from_min_size(bar.min + egui::vec2(x, 0.0), egui::vec2(slice, bar_height)),
+            0.0,
+            to_color32(color),
+        );
+    }
+
+    let mut changed = false;
+    for i in 0..gradient.len() {
+        let point = gradient.points()[i].point();
+        let x = egui::lerp(bar.left()..=bar.right(), point);
+        let handle = egui::Rect::from_center_size(
+            egui::pos2(x, bar.bottom() + handle_size * 0.5),
+            egui::Vec2::splat(handle_size),
+        );
+
+        let handle_response = ui
+            .interact(handle, response.id.with(i), egui::Sense::drag())
+            .on_hover_text_at_pointer("Drag to move.");
+        if handle_response.dragged() {
+            if let Some(pointer) = handle_response.interact_pointer_pos() {
+                changed |= move_point(gradient, i, (pointer.x - bar.left()) / width);
+            }
+        }
+
+        let visuals = ui.style().interact(&handle_response);
+        ui.painter().add(egui::Shape::convex_polygon(
+            vec![
+                egui::pos2(x, bar.bottom()),
+                egui::pos2(x + handle_size * 0.5, handle.bottom()),
+                egui::pos2(x - handle_size * 0.5, handle.bottom()),
+            ],
+            visuals.fg_stroke.color,
+            visuals.bg_stroke,
+        ));
+    }
+
+    changed
+}
+
+
+fn gradient_editor_widget(ui: &mut egui::Ui, gradient: &mut ColorGradient) -> egui::Response {
+    let mut changed = false;
+
+    let mut response = ui
+        .vertical(|ui| {
+            changed |= preview_bar(ui, gradient);
+
+            let mut removed = None;
+            egui::Grid::new(ui.id().with("stops"))
+                .num_columns(3)
+                .show(ui, |ui| {
+                    for i in 0..gradient.len() {
+                        let stop = gradient.points()[i];
+
+                        let mut point = stop.point();
+                        let drag = egui::DragValue::new(&mut point)
+                            .speed(0.005)
+                            .clamp_range(0.0..=1.0)
+                            .fixed_decimals(3);
+                        if ui.add(drag).changed() {
+                            changed |= move_point(gradient, i, point);
+                        }
+
+                        let mut color = stop.value();
+                        if ui.add(color_picker(&mut color)).changed() {
+                            gradient.insert(ColorPoint::new(stop.point(), color));
+                            changed = true;
+                        }
+
+                        // Keep at least one sampling point, so the gradient always has a color.
+                        if ui
+                            .add_enabled(gradient.len() > 1, egui::Button::new("Remove"))
+                            .clicked()
+                        {
+                            removed = Some(stop.point());
+                        }
+                        ui.end_row();
+                    }
+                });
+
+            if let Some(point) = removed {
+                changed |= gradient.remove(point);
+            }
+
+            if ui
+                .button("Add")
+                .on_hover_text_at_pointer("Add a sampling point in the widest gap.")
+                .clicked()
+            {
+                changed |= add_point(gradient);
+            }
+        })
+        .response;
+
+    if changed {
+        response.mark_changed();
+    }
+
+    response
+}
+
+
+/// Insert a sampling point in the middle of the widest gap between sampling points, including the
+/// ends of the gradient, colored as the gradient is there. Returns `true` if a point was added.
+fn add_point(gradient: &mut ColorGradient) -> bool {
+    let mut edges = vec![0.0];
+    edges.extend(gradient.points().iter().map(ColorPoint::point));
+    edges.push(1.0);
+
+    let Some((left, right)) = edges
+        .windows(2)
+        .map(|pair| (pair[0], pair[1]))
+        .max_by(|a, b| (a.1 - a.0).total_cmp(&(b.1 - b.0)))
+    else {
+        return false;
+    };
+    if right - left < MIN_SPACING * 2.0 {
+        return false;
+    }
+
+    let point = (left + right) * 0.5;
+    gradient.insert(ColorPoint::new(point, gradient.sample(point)));
+
+    true
+}
+
+
+/// Edit the sampling points of a gradient, with a preview of the gradient. The response is marked
+/// as changed whenever the gradient is changed.
+pub fn gradient_editor(gradient: &mut ColorGradient) -> impl egui::Widget + '_ {
+    move |ui: &mut egui::Ui| gradient_editor_widget(ui, gradient)
+}
@@ -0,0 +1,54 @@
+//
+// Copyright (c) 2023 Martin Green. All rights reserved.
+//
+
+use bevy::math::IVec2;
+use bevy_egui::egui;
+
+
+/// Largest side of a cell in the preview, so tiny patterns don't turn into blocks.
+const MAX_CELL_SIZE: f32 = 4.0;
+
+
+fn pattern_preview_widget(ui: &mut egui::Ui, cells: &[IVec2]) -> egui::Response {
+    let side = ui.spacing().interact_size.y * 2.0;
+    let (rect, response) = ui.allocate_exact_size(egui::Vec2::splat(side), egui::Sense::hover());
+    if !ui.is_rect_visible(rect) {
+        return response;
+    }
+
+    let visuals = ui.visuals();
+    ui.painter()
+        .rect_filled(rect, 0.0, visuals.extreme_bg_color);
+
+    let (Some(min), Some(max)) = (
+        cells.iter().copied().reduce(IVec2::min),
+        cells.iter().copied().reduce(IVec2::max),
+    ) else {
+        return response;
+    };
+
+    // Scale the pattern to fit, keeping its cells square, and center it.
+    let size = (max - min + IVec2::ONE).as_vec2();
+    let cell_size = (side / size.max_element()).min(MAX_CELL_SIZE);
+    let origin = rect.center() - egui::vec2(size.x, size.y) * cell_size * 0.5;
+
+    for xy in cells {
+        // Patterns are y-up, the screen is y-down.
+        #[allow(clippy::cast_precision_loss)]
+        let min = origin + egui::vec2((xy.x - min.x) as f32, (max.y - xy.y) as f32) * cell_size;
+        ui.painter().rect_filled(
+            egui::Rect::from_min_size(min, egui::Vec2::splat(cell_size)),
+            0.0,
+            visuals.strong_text_color(),
+        );
+    }
+
+    response
+}
+
+
+/// Draw a small thumbnail of the live `cells` of a pattern, scaled to fit.
+pub fn pattern_preview(cells: &[IVec2]) -> impl egui::Widget + '_ {
+    move |ui: &mut egui::Ui| pattern_preview_widget(ui, cells)
+}